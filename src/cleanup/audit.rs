@@ -0,0 +1,109 @@
+// Deletion audit log
+// Records who deleted what, when, why and how it went
+
+use super::guard::Refusal;
+use super::remove::RemovalReport;
+use crate::db::store::{ArtifactRecord, DeletionRecord};
+use chrono::Utc;
+use std::env;
+
+/// Deleted from the TUI, one artifact at a time.
+pub const TRIGGER_MANUAL: &str = "manual";
+/// Deleted from the plan shown by Shift+D.
+pub const TRIGGER_CLEAR_ALL: &str = "clear_all";
+/// Deleted by automatic removal or `ratifact clean` for being past the retention period.
+pub const TRIGGER_RETENTION: &str = "retention";
+/// Purged from quarantine once its grace period ran out.
+pub const TRIGGER_QUARANTINE_EXPIRY: &str = "quarantine_expiry";
+
+/// What a deletion did to one artifact.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Removed,
+    /// Nothing was left to remove.
+    AlreadyGone,
+    Quarantined,
+    /// Some of it was removed, the rest was left.
+    Partial(String),
+    Refused(String),
+    /// Nothing of it could be removed or moved.
+    Failed(String),
+}
+
+impl Outcome {
+    /// The outcome of removing a tree, given what was removed and left.
+    pub fn of_removal(report: &RemovalReport) -> Self {
+        let Some(first) = report.failures.first() else {
+            return Outcome::Removed;
+        };
+        let mut detail = format!("{}: {}", first.path.display(), first.error);
+        if report.failures.len() > 1 {
+            detail.push_str(&format!(" (and {} more)", report.failures.len() - 1));
+        }
+        if let Some(error) = &report.escalation_error {
+            detail.push_str(&format!("; could not escalate: {}", error));
+        }
+        if report.removed_files + report.removed_dirs > 0 || !report.escalated.is_empty() {
+            Outcome::Partial(detail)
+        } else {
+            Outcome::Failed(detail)
+        }
+    }
+
+    pub fn refused(refusal: &Refusal) -> Self {
+        Outcome::Refused(refusal.to_string())
+    }
+
+    /// Whether nothing is left where the artifact was.
+    pub fn is_gone(&self) -> bool {
+        matches!(self, Outcome::Removed | Outcome::AlreadyGone | Outcome::Quarantined)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Removed | Outcome::AlreadyGone => "removed",
+            Outcome::Quarantined => "quarantined",
+            Outcome::Partial(_) => "partial",
+            Outcome::Refused(_) => "refused",
+            Outcome::Failed(_) => "failed",
+        }
+    }
+
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            Outcome::Removed | Outcome::Quarantined => None,
+            Outcome::AlreadyGone => Some("already gone from disk".to_string()),
+            Outcome::Partial(detail) | Outcome::Refused(detail) | Outcome::Failed(detail) => Some(detail.clone()),
+        }
+    }
+}
+
+/// The audit entry for deleting `path` now, as the current user. Sizes come
+/// from the artifact's record, or are zero when it has none.
+pub fn deletion(
+    path: &str,
+    record: Option<&ArtifactRecord>,
+    trigger: &str,
+    outcome: &Outcome,
+    reason: Option<&str>,
+) -> DeletionRecord {
+    DeletionRecord {
+        path: path.to_string(),
+        size_bytes: record.map_or(0, |record| record.size_bytes),
+        disk_bytes: record.and_then(|record| record.disk_bytes),
+        trigger: trigger.to_string(),
+        user: current_user(),
+        deleted_at: Utc::now(),
+        outcome: outcome.as_str().to_string(),
+        detail: outcome.detail(),
+        reason: reason.map(str::to_string),
+    }
+}
+
+/// The user to hold responsible: whoever ran sudo when running under it.
+pub fn current_user() -> String {
+    ["SUDO_USER", "USER", "USERNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,83 @@
+// Deletion guard
+// Nothing is deleted unless it resolves to a path inside a configured scan path
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why a path was not deleted.
+#[derive(Debug)]
+pub enum Refusal {
+    /// The path could not be resolved, e.g. a dangling symlink.
+    Unresolvable { path: String, source: io::Error },
+    /// Resolving symlinks and `..` leads outside every scan path.
+    OutsideScanPaths { path: String, resolved: PathBuf },
+    /// The path is one of the scan paths itself.
+    ScanRoot { path: String },
+}
+
+impl Refusal {
+    pub fn path(&self) -> &str {
+        match self {
+            Refusal::Unresolvable { path, .. } | Refusal::OutsideScanPaths { path, .. } | Refusal::ScanRoot { path } => path,
+        }
+    }
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::Unresolvable { path, source } => write!(f, "refusing to delete {}: cannot resolve it ({})", path, source),
+            Refusal::OutsideScanPaths { path, resolved } => write!(
+                f,
+                "refusing to delete {}: it resolves to {}, which is outside every scan path",
+                path,
+                resolved.display()
+            ),
+            Refusal::ScanRoot { path } => write!(f, "refusing to delete {}: it is a scan path", path),
+        }
+    }
+}
+
+impl std::error::Error for Refusal {}
+
+/// The canonical scan paths deletions must stay inside.
+#[derive(Debug, Clone)]
+pub struct PathGuard {
+    roots: Vec<PathBuf>,
+}
+
+impl PathGuard {
+    /// Scan paths that do not exist can hold nothing and are left out.
+    pub fn new(scan_paths: &[String]) -> Self {
+        PathGuard { roots: scan_paths.iter().filter_map(|path| fs::canonicalize(path).ok()).collect() }
+    }
+
+    /// The innermost scan path a canonical path lies in.
+    pub fn root_of(&self, resolved: &Path) -> Option<&Path> {
+        self.roots
+            .iter()
+            .filter(|root| resolved.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    /// The canonical form of `path` if it is strictly inside a scan path.
+    pub fn check(&self, path: &str) -> Result<PathBuf, Refusal> {
+        let resolved = fs::canonicalize(path).map_err(|source| Refusal::Unresolvable { path: path.to_string(), source })?;
+        if self.roots.iter().any(|root| root == &resolved) {
+            return Err(Refusal::ScanRoot { path: path.to_string() });
+        }
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(Refusal::OutsideScanPaths { path: path.to_string(), resolved })
+        }
+    }
+}
+
+/// Whether nothing is left at `path`, not even a dangling symlink.
+pub fn is_gone(path: &Path) -> bool {
+    matches!(fs::symlink_metadata(path), Err(e) if e.kind() == io::ErrorKind::NotFound)
+}
@@ -0,0 +1,107 @@
+// Cleanup lock
+// Keeps the TUI, `ratifact clean` and the daemon from purging at the same time
+
+use crate::db::store::{ArtifactStore, Lock};
+use crate::utils::logger::log_to_file;
+use chrono::{Duration, Utc};
+use std::fmt;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// Name of the lock every bulk deletion takes.
+pub const CLEANUP_LOCK: &str = "cleanup";
+
+/// How long the lock holds before another process may take it over, should
+/// its holder die without releasing it.
+pub const LOCK_LEASE_MINUTES: i64 = 60;
+
+/// How often a live holder extends its lease, well within one lease.
+pub const LOCK_RENEW_MINUTES: u64 = 15;
+
+/// The cleanup lock, held until released. Its lease is renewed in the
+/// background for as long as it is held.
+#[derive(Debug)]
+pub struct CleanupLock {
+    holder: String,
+    renewal: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Someone else is cleaning up.
+    Busy(Lock),
+    Store(sqlx::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Busy(lock) => write!(
+                f,
+                "another cleanup is running: {} since {}",
+                lock.holder,
+                lock.acquired_at.format("%Y-%m-%d %H:%M")
+            ),
+            LockError::Store(e) => write!(f, "could not take the cleanup lock: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+impl From<sqlx::Error> for LockError {
+    fn from(e: sqlx::Error) -> Self {
+        LockError::Store(e)
+    }
+}
+
+impl CleanupLock {
+    /// Takes the lock for `role`, such as "daemon" or "tui", in this process.
+    pub async fn acquire(store: &Arc<dyn ArtifactStore>, role: &str) -> Result<Self, LockError> {
+        let now = Utc::now();
+        let lock = Lock {
+            name: CLEANUP_LOCK.to_string(),
+            holder: format!("{} (pid {})", role, std::process::id()),
+            acquired_at: now,
+            expires_at: now + Duration::minutes(LOCK_LEASE_MINUTES),
+        };
+        match store.acquire_lock(&lock).await? {
+            Some(held) => Err(LockError::Busy(held)),
+            None => {
+                let renewal = tokio::spawn(renew(Arc::clone(store), lock.holder.clone()));
+                Ok(CleanupLock { holder: lock.holder, renewal })
+            }
+        }
+    }
+
+    pub async fn release(self, store: &dyn ArtifactStore) -> Result<(), sqlx::Error> {
+        self.renewal.abort();
+        store.release_lock(CLEANUP_LOCK, &self.holder).await?;
+        Ok(())
+    }
+}
+
+impl Drop for CleanupLock {
+    fn drop(&mut self) {
+        // A lock dropped without release lapses after one lease
+        self.renewal.abort();
+    }
+}
+
+/// Keeps extending the lease of `holder` until the lock is released, so a
+/// long purge is never taken over while it runs.
+async fn renew(store: Arc<dyn ArtifactStore>, holder: String) {
+    let period = std::time::Duration::from_secs(LOCK_RENEW_MINUTES * 60);
+    loop {
+        tokio::time::sleep(period).await;
+        let expires_at = Utc::now() + Duration::minutes(LOCK_LEASE_MINUTES);
+        match store.renew_lock(CLEANUP_LOCK, &holder, expires_at).await {
+            Ok(0) => {
+                log_to_file(&format!("The cleanup lock of {} was taken over", holder));
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => log_to_file(&format!("Renewing the cleanup lock failed: {:?}", e)),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// Artifact deletion
+// Shared by the TUI, automatic removal and the headless CLI
+
+pub mod audit;
+pub mod guard;
+pub mod lock;
+pub mod plan;
+pub mod policy;
+pub mod quarantine;
+pub mod remove;
+
+use crate::config::Config;
+use crate::db::store::{ArtifactRecord, ArtifactStore};
+use audit::Outcome;
+use guard::{PathGuard, Refusal, is_gone};
+use lock::{CleanupLock, LockError};
+use policy::{Decision, RetentionPolicy};
+use quarantine::QuarantineError;
+use remove::{RemovalReport, remove_tree};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Outcome of a retention purge.
+#[derive(Debug, Default)]
+pub struct PurgeSummary {
+    pub removed: Vec<String>,
+    /// Moved into quarantine instead of removed.
+    pub quarantined: Vec<String>,
+    pub failed: Vec<String>,
+    /// Paths the guard would not delete; they stay recorded.
+    pub refused: Vec<Refusal>,
+    /// What was removed from disk and left behind across all artifacts.
+    pub total: RemovalReport,
+}
+
+/// Deletes the artifacts `policy` picks from disk, or moves them into
+/// quarantine, and forgets the ones that are gone. Paths resolving outside
+/// `guard` are refused. Never escalates; paths denied to the current user are reported as failed.
+pub async fn purge_by_policy(
+    store: &dyn ArtifactStore,
+    policy: &RetentionPolicy,
+    guard: &PathGuard,
+    quarantine: bool,
+) -> Result<PurgeSummary, sqlx::Error> {
+    let decisions = policy::retention_decisions(store, policy).await?;
+    purge_decisions(store, decisions, guard, quarantine, audit::TRIGGER_RETENTION).await
+}
+
+/// A retention purge followed by purging expired quarantine, under the
+/// cleanup lock taken for `role`. Returns both summaries, in that order.
+pub async fn purge_locked(
+    store: &Arc<dyn ArtifactStore>,
+    policy: &RetentionPolicy,
+    config: &Config,
+    role: &str,
+) -> Result<(PurgeSummary, PurgeSummary), LockError> {
+    let lock = CleanupLock::acquire(store, role).await?;
+    let store = store.as_ref();
+    let guard = PathGuard::new(&config.scan_paths);
+    let result = match purge_by_policy(store, policy, &guard, config.quarantine).await {
+        Ok(summary) => quarantine::purge_expired(store, config.quarantine_days, &guard).await.map(|expired| (summary, expired)),
+        Err(e) => Err(e),
+    };
+    lock.release(store).await?;
+    Ok(result?)
+}
+
+/// Deletes or quarantines the artifacts a policy picked and forgets the ones
+/// that are gone. Each is written to the audit log under `trigger`, with the
+/// decision's reason.
+pub async fn purge_decisions(
+    store: &dyn ArtifactStore,
+    decisions: Vec<Decision>,
+    guard: &PathGuard,
+    quarantine: bool,
+    trigger: &str,
+) -> Result<PurgeSummary, sqlx::Error> {
+    let records: HashMap<String, ArtifactRecord> =
+        store.current_artifacts().await?.into_iter().map(|record| (record.path.clone(), record)).collect();
+    let mut summary = PurgeSummary::default();
+    for Decision { path, reason, .. } in decisions {
+        let outcome = if quarantine && !is_gone(Path::new(&path)) {
+            match quarantine::quarantine(store, guard, &path).await {
+                Ok(_) => Outcome::Quarantined,
+                Err(QuarantineError::Refused(refusal)) => {
+                    let outcome = Outcome::refused(&refusal);
+                    summary.refused.push(refusal);
+                    outcome
+                }
+                Err(QuarantineError::Store(e)) => return Err(e),
+                Err(e) => {
+                    summary.failed.push(path.clone());
+                    Outcome::Failed(e.to_string())
+                }
+            }
+        } else {
+            remove_guarded(&path, guard, &mut summary)
+        };
+        store.record_deletion(&audit::deletion(&path, records.get(&path), trigger, &outcome, Some(&reason))).await?;
+        match outcome {
+            Outcome::Quarantined => summary.quarantined.push(path),
+            Outcome::Removed | Outcome::AlreadyGone => {
+                store.delete_artifact(&path).await?;
+                summary.removed.push(path);
+            }
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
+
+/// Removes `path` if the guard allows it, adding refusals and leftovers to
+/// `summary`.
+fn remove_guarded(path: &str, guard: &PathGuard, summary: &mut PurgeSummary) -> Outcome {
+    if is_gone(Path::new(path)) {
+        return Outcome::AlreadyGone;
+    }
+    let resolved = match guard.check(path) {
+        Ok(resolved) => resolved,
+        Err(refusal) => {
+            let outcome = Outcome::refused(&refusal);
+            summary.refused.push(refusal);
+            return outcome;
+        }
+    };
+    let report = remove_tree(&resolved);
+    let outcome = Outcome::of_removal(&report);
+    summary.total.merge(report);
+    if !outcome.is_gone() {
+        summary.failed.push(path.to_string());
+    }
+    outcome
+}
+
+pub fn has_unusual_files(path: &str) -> bool {
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if name.contains("bundle") || name.ends_with(".exe") || name.ends_with(".bin") {
+                return true;
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,153 @@
+// Deletion plans
+// What a clear-all or retention pass would delete, shown before anything is touched
+
+use super::policy::{Decision, LastUse, RetentionPolicy, Signal};
+use crate::db::store::{ArtifactRecord, ArtifactStore};
+use crate::rules::RuleSet;
+use crate::rules::cachedir::read_cachedir_tag;
+use crate::scanner::disk_usage::disk_space;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One artifact a plan would delete, and why it was picked.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedArtifact {
+    pub path: String,
+    pub project_path: String,
+    pub language: String,
+    pub size_bytes: u64,
+    /// Allocated size, or the apparent size until the artifact is rescanned.
+    pub disk_bytes: u64,
+    pub first_seen: DateTime<Utc>,
+    /// Whole days since the artifact was first seen.
+    pub age_days: i64,
+    /// The rule or CACHEDIR.TAG that marks the directory as build output.
+    pub rule: String,
+    pub verified: bool,
+    pub last_used: DateTime<Utc>,
+    /// The signal `last_used` comes from.
+    pub last_used_signal: Signal,
+    /// Whole days since the artifact was last used.
+    pub idle_days: i64,
+    /// Why a policy picked the artifact; None when it was picked by hand.
+    pub reason: Option<String>,
+    /// The `[[policies]]` block that applied; None for the global settings.
+    pub policy: Option<String>,
+    /// Unselected artifacts are kept when the plan runs.
+    #[serde(skip)]
+    pub selected: bool,
+}
+
+/// Artifacts a deletion would remove, biggest first. Verified ones are selected
+/// to begin with; unverified ones only go when picked one by one.
+#[derive(Debug, Clone, Default)]
+pub struct DeletionPlan {
+    pub artifacts: Vec<PlannedArtifact>,
+    /// Pinned artifacts left out of the plan.
+    pub pinned: usize,
+}
+
+impl DeletionPlan {
+    /// A plan for `records`, leaving out the pinned ones.
+    pub fn new(records: &[ArtifactRecord], rules: &RuleSet, now: DateTime<Utc>) -> Self {
+        let pinned = records.iter().filter(|record| record.is_pinned(now)).count();
+        let mut artifacts: Vec<PlannedArtifact> = records
+            .iter()
+            .filter(|record| !record.is_pinned(now))
+            .map(|record| (record, LastUse::of(record)))
+            .map(|(record, last_use)| PlannedArtifact {
+                path: record.path.clone(),
+                project_path: record.project_path.clone(),
+                language: record.language.clone(),
+                size_bytes: record.size_bytes,
+                disk_bytes: record.disk_bytes.unwrap_or(record.size_bytes),
+                first_seen: record.first_seen,
+                age_days: (now - record.first_seen).num_days(),
+                rule: selecting_rule(record, rules),
+                verified: record.verified,
+                last_used: last_use.at,
+                last_used_signal: last_use.signal,
+                idle_days: last_use.idle_days(now),
+                reason: None,
+                policy: None,
+                selected: record.verified,
+            })
+            .collect();
+        artifacts.sort_by(|a, b| b.disk_bytes.cmp(&a.disk_bytes).then_with(|| a.path.cmp(&b.path)));
+        DeletionPlan { artifacts, pinned }
+    }
+
+    /// Every known artifact but the pinned ones, as cleared by Shift+D.
+    pub async fn clear_all(store: &dyn ArtifactStore, rules: &RuleSet) -> Result<Self, sqlx::Error> {
+        Ok(Self::new(&store.current_artifacts().await?, rules, Utc::now()))
+    }
+
+    /// The artifacts a retention pass would delete, each with the policy's
+    /// reason and the block that applied.
+    pub async fn retention(store: &dyn ArtifactStore, policy: &RetentionPolicy, rules: &RuleSet) -> Result<Self, sqlx::Error> {
+        let records = store.current_artifacts().await?;
+        let now = Utc::now();
+        let mut decisions: HashMap<String, Decision> = policy
+            .evaluate(&records, now, |path| disk_space(Path::new(path)))
+            .into_iter()
+            .map(|decision| (decision.path.clone(), decision))
+            .collect();
+        let pinned = records.iter().filter(|record| record.is_pinned(now)).count();
+        let records: Vec<ArtifactRecord> = records.into_iter().filter(|record| decisions.contains_key(&record.path)).collect();
+        let mut plan = Self::new(&records, rules, now);
+        plan.pinned = pinned;
+        for artifact in &mut plan.artifacts {
+            if let Some(decision) = decisions.remove(&artifact.path) {
+                artifact.reason = Some(decision.reason);
+                artifact.policy = decision.policy;
+            }
+        }
+        Ok(plan)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.artifacts.is_empty()
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(artifact) = self.artifacts.get_mut(index) {
+            artifact.selected = !artifact.selected;
+        }
+    }
+
+    /// Selects every verified artifact, or nothing when they all already are.
+    pub fn toggle_all(&mut self) {
+        let select = !self.artifacts.iter().filter(|artifact| artifact.verified).all(|artifact| artifact.selected);
+        for artifact in &mut self.artifacts {
+            artifact.selected = select && artifact.verified;
+        }
+    }
+
+    /// Unverified artifacts nobody picked, kept when the plan runs.
+    pub fn unverified_kept(&self) -> usize {
+        self.artifacts.iter().filter(|artifact| !artifact.verified && !artifact.selected).count()
+    }
+
+    pub fn selected(&self) -> impl Iterator<Item = &PlannedArtifact> {
+        self.artifacts.iter().filter(|artifact| artifact.selected)
+    }
+
+    /// Apparent and allocated bytes the selected artifacts hold.
+    pub fn selected_bytes(&self) -> (u64, u64) {
+        self.selected().fold((0, 0), |(size, disk), artifact| (size + artifact.size_bytes, disk + artifact.disk_bytes))
+    }
+}
+
+fn selecting_rule(record: &ArtifactRecord, rules: &RuleSet) -> String {
+    let path = Path::new(&record.path);
+    if let Some(rule) = rules.rule_for(Path::new(&record.project_path), path) {
+        rule
+    } else if read_cachedir_tag(path).is_some() {
+        "CACHEDIR.TAG".to_string()
+    } else {
+        // The project's markers are gone; all we have is what the scan recorded
+        format!("{} (as recorded)", record.language)
+    }
+}
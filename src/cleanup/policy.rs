@@ -0,0 +1,393 @@
+// Retention policies
+// Decides which artifacts to delete, by last use, size limits and free space
+
+use crate::config::Config;
+use crate::db::store::{ArtifactRecord, ArtifactStore};
+use crate::scanner::disk_usage::{DiskSpace, disk_space};
+use crate::utils::format_size;
+use chrono::{DateTime, Duration, Utc};
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::Path;
+
+/// A sign of when an artifact was last used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Signal {
+    /// Newest modification time of anything inside the artifact.
+    ContentModified,
+    /// Newest modification time of the project's own files.
+    SourceModified,
+    /// The last git commit touching the project.
+    LastCommit,
+    /// Nothing was recorded yet, so the first time ratifact saw it stands in.
+    FirstSeen,
+}
+
+impl Signal {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Signal::ContentModified => "content_modified",
+            Signal::SourceModified => "source_modified",
+            Signal::LastCommit => "last_commit",
+            Signal::FirstSeen => "first_seen",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Signal::ContentModified => "newest file in the artifact",
+            Signal::SourceModified => "newest project source file",
+            Signal::LastCommit => "last git commit",
+            Signal::FirstSeen => "first seen by ratifact",
+        }
+    }
+}
+
+/// When an artifact was last used, and the signal that says so.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LastUse {
+    pub at: DateTime<Utc>,
+    pub signal: Signal,
+}
+
+impl LastUse {
+    /// The most recent of the recorded signals, since any of them moving means
+    /// the artifact or its project is in use. Falls back to when it was first seen.
+    pub fn of(record: &ArtifactRecord) -> Self {
+        let signals = &record.signals;
+        [
+            (signals.content_modified, Signal::ContentModified),
+            (signals.source_modified, Signal::SourceModified),
+            (signals.last_commit, Signal::LastCommit),
+        ]
+        .into_iter()
+        .filter_map(|(at, signal)| at.map(|at| LastUse { at, signal }))
+        .max_by_key(|last_use| last_use.at)
+        .unwrap_or(LastUse { at: record.first_seen, signal: Signal::FirstSeen })
+    }
+
+    /// Whole days since the last use.
+    pub fn idle_days(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.at).num_days()
+    }
+}
+
+/// The limit that picked an artifact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Unused for longer than `retention_days`.
+    Age,
+    /// Beyond `max_artifacts_per_project`.
+    PerProject,
+    /// Over `max_total_size` across all artifacts.
+    TotalSize,
+    /// Its filesystem has less than `min_free_percent` available.
+    FreeSpace,
+}
+
+/// An artifact a policy picked for deletion, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub path: String,
+    pub last_use: LastUse,
+    pub limit: Limit,
+    /// The `[[policies]]` block that applied, None for the global settings.
+    pub policy: Option<String>,
+    /// Names the limit and the signal that decided, e.g. "unused for 45 days (last git commit, 2025-01-02)".
+    pub reason: String,
+}
+
+/// A `[[policies]]` block: retention for the artifacts it matches in place of
+/// the global `retention_days`. Each list matches if any entry does, an empty
+/// one matches everything, and all of them must match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyBlock {
+    /// Shown in the deletion plan and the settings panel.
+    pub name: String,
+    /// Globs matched against the artifact path, e.g. `~/scratch/**`.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Languages as recorded by scans, e.g. `JavaScript`.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Artifact directories relative to their project, e.g. `node_modules`,
+    /// or with the language as the deletion plan shows them, e.g. `Rust: target`.
+    #[serde(default)]
+    pub rules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u32>,
+    /// Never delete the matched artifacts automatically.
+    #[serde(default)]
+    pub keep: bool,
+}
+
+impl PolicyBlock {
+    /// Checks a user-supplied block, returning a message on the first problem.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.paths.is_empty() && self.languages.is_empty() && self.rules.is_empty() {
+            return Err(format!("policy '{}' needs paths, languages or rules to match", self.name));
+        }
+        match (self.retention_days, self.keep) {
+            (None, false) => return Err(format!("policy '{}' needs retention_days or keep = true", self.name)),
+            (Some(_), true) => return Err(format!("policy '{}' cannot set both retention_days and keep", self.name)),
+            (Some(0), _) => return Err(format!("policy '{}': retention_days must be at least 1 day", self.name)),
+            _ => {}
+        }
+        for path in &self.paths {
+            Pattern::new(&expand_home(path)).map_err(|e| format!("policy '{}': path '{}': {}", self.name, path, e))?;
+        }
+        Ok(())
+    }
+
+    /// What the block does, e.g. "3 days" or "never removed".
+    pub fn summary(&self) -> String {
+        match self.retention_days {
+            Some(days) if !self.keep => format!("{} days", days),
+            _ => "never removed".to_string(),
+        }
+    }
+}
+
+/// A block with its path globs compiled.
+#[derive(Debug, Clone, PartialEq)]
+struct Scope {
+    block: PolicyBlock,
+    paths: Vec<Pattern>,
+}
+
+impl Scope {
+    fn new(block: &PolicyBlock) -> Self {
+        let paths = block.paths.iter().filter_map(|path| Pattern::new(&expand_home(path)).ok()).collect();
+        Scope { block: block.clone(), paths }
+    }
+
+    fn matches(&self, record: &ArtifactRecord) -> bool {
+        // `*` stays within one directory, `**` crosses them
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        let relative = Path::new(&record.path)
+            .strip_prefix(&record.project_path)
+            .map(|relative| relative.display().to_string())
+            .unwrap_or_default();
+        let labelled = format!("{}: {}", record.language, relative);
+        (self.paths.is_empty() || self.paths.iter().any(|path| path.matches_with(&record.path, options)))
+            && (self.block.languages.is_empty()
+                || self.block.languages.iter().any(|language| language.eq_ignore_ascii_case(&record.language)))
+            && (self.block.rules.is_empty() || self.block.rules.iter().any(|rule| *rule == relative || *rule == labelled))
+    }
+}
+
+/// Expands a leading `~/` to the home directory.
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Which artifacts cleanup deletes. Only verified artifacts are ever picked,
+/// least recently used first; unverified ones, and the ones a block keeps,
+/// still count towards the limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    pub retention_days: u32,
+    pub max_total_bytes: Option<u64>,
+    pub min_free_percent: Option<u8>,
+    pub max_per_project: Option<usize>,
+    /// Checked in order; the first matching block applies.
+    scopes: Vec<Scope>,
+}
+
+impl RetentionPolicy {
+    /// Keeps what was used within `retention_days`, with no other limit.
+    pub fn new(retention_days: u32) -> Self {
+        RetentionPolicy { retention_days, max_total_bytes: None, min_free_percent: None, max_per_project: None, scopes: vec![] }
+    }
+
+    /// Adds `blocks` after the ones already set.
+    pub fn with_blocks(mut self, blocks: &[PolicyBlock]) -> Self {
+        self.scopes.extend(blocks.iter().map(Scope::new));
+        self
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &PolicyBlock> {
+        self.scopes.iter().map(|scope| &scope.block)
+    }
+
+    /// The block that applies to `record`, if any.
+    pub fn block_for(&self, record: &ArtifactRecord) -> Option<&PolicyBlock> {
+        self.scopes.iter().find(|scope| scope.matches(record)).map(|scope| &scope.block)
+    }
+
+    /// Which block applies to `record` and what it does, e.g. "scratch: 3 days"
+    /// or "default: 30 days".
+    pub fn describe_for(&self, record: &ArtifactRecord) -> String {
+        match self.block_for(record) {
+            Some(block) => format!("{}: {}", block.name, block.summary()),
+            None => format!("default: {} days", self.retention_days),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        RetentionPolicy {
+            max_total_bytes: config.max_total_bytes(),
+            min_free_percent: config.min_free_percent,
+            max_per_project: config.max_artifacts_per_project,
+            ..Self::new(config.retention_days)
+        }
+        .with_blocks(&config.policies)
+    }
+
+    /// The limits beside retention days, for display, e.g. "50.0 GB total, 10% free".
+    pub fn describe_limits(&self) -> Option<String> {
+        let limits: Vec<String> = [
+            self.max_total_bytes.map(|bytes| format!("{} total", format_size(bytes))),
+            self.min_free_percent.map(|percent| format!("{}% free", percent)),
+            self.max_per_project.map(|count| format!("{} per project", count)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!limits.is_empty()).then(|| limits.join(", "))
+    }
+
+    /// Artifacts to delete, least recently used first. Limits apply in turn:
+    /// age, by the matching block's retention if there is one, then the count
+    /// per project, the total size and the free space of each filesystem, as
+    /// reported by `space`. Each limit only counts what the earlier ones left.
+    /// Pinned artifacts count toward the limits but are never picked.
+    pub fn evaluate(
+        &self,
+        records: &[ArtifactRecord],
+        now: DateTime<Utc>,
+        space: impl Fn(&str) -> Option<DiskSpace>,
+    ) -> Vec<Decision> {
+        let mut candidates: Vec<Candidate> = records
+            .iter()
+            .map(|record| Candidate { record, last_use: LastUse::of(record), block: self.block_for(record) })
+            .collect();
+        candidates.sort_by(|a, b| a.last_use.at.cmp(&b.last_use.at).then_with(|| a.record.path.cmp(&b.record.path)));
+        let mut picked: HashSet<&str> = HashSet::new();
+        let mut decisions = Vec::new();
+
+        for candidate in &candidates {
+            let days = candidate.block.and_then(|block| block.retention_days).unwrap_or(self.retention_days);
+            if candidate.deletable(now) && candidate.last_use.at < now - Duration::days(days as i64) {
+                picked.insert(&candidate.record.path);
+                decisions.push(candidate.decide(Limit::Age, None, now));
+            }
+        }
+
+        if let Some(max) = self.max_per_project {
+            let mut kept: HashMap<&str, usize> = HashMap::new();
+            for candidate in candidates.iter().filter(|candidate| !picked.contains(candidate.record.path.as_str())) {
+                *kept.entry(&candidate.record.project_path).or_default() += 1;
+            }
+            for candidate in &candidates {
+                if !candidate.deletable(now) || picked.contains(candidate.record.path.as_str()) {
+                    continue;
+                }
+                if let Some(count) = kept.get_mut(candidate.record.project_path.as_str())
+                    && *count > max
+                {
+                    *count -= 1;
+                    picked.insert(&candidate.record.path);
+                    let why = format!("over {} artifacts in its project", max);
+                    decisions.push(candidate.decide(Limit::PerProject, Some(why), now));
+                }
+            }
+        }
+
+        if let Some(max) = self.max_total_bytes {
+            let mut total: u64 = candidates
+                .iter()
+                .filter(|candidate| !picked.contains(candidate.record.path.as_str()))
+                .map(|candidate| disk_bytes(candidate.record))
+                .sum();
+            for candidate in &candidates {
+                if total <= max {
+                    break;
+                }
+                if candidate.deletable(now) && !picked.contains(candidate.record.path.as_str()) {
+                    total -= disk_bytes(candidate.record);
+                    picked.insert(&candidate.record.path);
+                    let why = format!("artifacts over {} in total", format_size(max));
+                    decisions.push(candidate.decide(Limit::TotalSize, Some(why), now));
+                }
+            }
+        }
+
+        if let Some(percent) = self.min_free_percent {
+            // Bytes still to free per filesystem, and how much of it was free
+            let mut shortfall: HashMap<u64, (u64, f64)> = HashMap::new();
+            for candidate in &candidates {
+                if !candidate.deletable(now) || picked.contains(candidate.record.path.as_str()) {
+                    continue;
+                }
+                let Some(disk) = space(&candidate.record.path) else { continue };
+                let (missing, free) = shortfall.entry(disk.device).or_insert_with(|| {
+                    let wanted = disk.total_bytes / 100 * percent as u64;
+                    (wanted.saturating_sub(disk.available_bytes), disk.free_percent())
+                });
+                if *missing > 0 {
+                    *missing = missing.saturating_sub(disk_bytes(candidate.record));
+                    picked.insert(&candidate.record.path);
+                    let why = format!("{:.0}% free on its filesystem, below {}%", free.floor(), percent);
+                    decisions.push(candidate.decide(Limit::FreeSpace, Some(why), now));
+                }
+            }
+        }
+
+        decisions.sort_by(|a, b| a.last_use.at.cmp(&b.last_use.at).then_with(|| a.path.cmp(&b.path)));
+        decisions
+    }
+}
+
+/// An artifact being evaluated, with the block that applies to it.
+struct Candidate<'a> {
+    record: &'a ArtifactRecord,
+    last_use: LastUse,
+    block: Option<&'a PolicyBlock>,
+}
+
+impl Candidate<'_> {
+    /// Verified, not pinned and not kept by its block.
+    fn deletable(&self, now: DateTime<Utc>) -> bool {
+        self.record.verified && !self.record.is_pinned(now) && !self.block.is_some_and(|block| block.keep)
+    }
+
+    /// A decision whose reason leads with `why` the limit applies, if given,
+    /// e.g. "over 3 artifacts in its project; unused for 12 days (...)".
+    fn decide(&self, limit: Limit, why: Option<String>, now: DateTime<Utc>) -> Decision {
+        let last_use = self.last_use;
+        let unused = format!(
+            "unused for {} days ({}, {})",
+            last_use.idle_days(now),
+            last_use.signal.describe(),
+            last_use.at.format("%Y-%m-%d")
+        );
+        let reason = match why {
+            Some(why) => format!("{}; {}", why, unused),
+            None => unused,
+        };
+        let policy = self.block.map(|block| block.name.clone());
+        Decision { path: self.record.path.clone(), last_use, limit, policy, reason }
+    }
+}
+
+fn disk_bytes(record: &ArtifactRecord) -> u64 {
+    record.disk_bytes.unwrap_or(record.size_bytes)
+}
+
+/// What `policy` decides for the artifacts currently recorded, against the
+/// free space their filesystems have now.
+pub async fn retention_decisions(store: &dyn ArtifactStore, policy: &RetentionPolicy) -> Result<Vec<Decision>, sqlx::Error> {
+    let records = store.current_artifacts().await?;
+    Ok(policy.evaluate(&records, Utc::now(), |path| disk_space(Path::new(path))))
+}
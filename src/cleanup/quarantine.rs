@@ -0,0 +1,138 @@
+// Quarantine
+// Moves artifacts aside instead of deleting them, so they can be restored until purged
+
+use super::audit::{self, TRIGGER_QUARANTINE_EXPIRY};
+use super::guard::{PathGuard, Refusal, is_gone};
+use super::{PurgeSummary, remove_guarded};
+use crate::db::store::{ArtifactStore, DeletionRecord, DiskUsage, QuarantineEntry};
+use crate::scanner::disk_usage::device_of;
+use chrono::Utc;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the holding area; never scanned.
+pub const QUARANTINE_DIR: &str = ".ratifact-quarantine";
+
+/// Why an artifact could not be quarantined or restored.
+#[derive(Debug)]
+pub enum QuarantineError {
+    Refused(Refusal),
+    /// Only recorded artifacts can be quarantined, so they can be restored.
+    NotRecorded { path: String },
+    /// Something already exists where the artifact would be restored.
+    Occupied { path: String },
+    Io { path: String, source: io::Error },
+    Store(sqlx::Error),
+}
+
+impl fmt::Display for QuarantineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuarantineError::Refused(refusal) => refusal.fmt(f),
+            QuarantineError::NotRecorded { path } => write!(f, "cannot quarantine {}: it is not recorded, scan first", path),
+            QuarantineError::Occupied { path } => write!(f, "cannot restore {}: the path already exists", path),
+            QuarantineError::Io { path, source } => write!(f, "cannot move {}: {}", path, source),
+            QuarantineError::Store(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for QuarantineError {}
+
+impl From<Refusal> for QuarantineError {
+    fn from(refusal: Refusal) -> Self {
+        QuarantineError::Refused(refusal)
+    }
+}
+
+impl From<sqlx::Error> for QuarantineError {
+    fn from(e: sqlx::Error) -> Self {
+        QuarantineError::Store(e)
+    }
+}
+
+/// The holding area for an artifact at the canonical path `resolved`, inside
+/// `root`: the topmost directory still on the artifact's filesystem, so that
+/// moving there is a rename rather than a copy.
+pub fn holding_area(root: &Path, resolved: &Path) -> PathBuf {
+    let device = fs::symlink_metadata(resolved).ok().and_then(|metadata| device_of(&metadata));
+    let mut base = resolved.parent().unwrap_or(root);
+    while base != root
+        && let Some(parent) = base.parent()
+        && fs::metadata(parent).ok().and_then(|metadata| device_of(&metadata)) == device
+    {
+        base = parent;
+    }
+    base.join(QUARANTINE_DIR)
+}
+
+/// Moves the artifact at `path` into its holding area and records it as quarantined.
+pub async fn quarantine(store: &dyn ArtifactStore, guard: &PathGuard, path: &str) -> Result<QuarantineEntry, QuarantineError> {
+    let io_error = |path: &Path| {
+        let path = path.display().to_string();
+        move |source| QuarantineError::Io { path, source }
+    };
+    let resolved = guard.check(path)?;
+    let root = guard.root_of(&resolved).unwrap_or(Path::new("/"));
+    let area = holding_area(root, &resolved);
+    fs::create_dir_all(&area).map_err(io_error(&area))?;
+    let name = resolved.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let holding = area.join(format!("{}-{}", Utc::now().format("%Y%m%dT%H%M%S%.9f"), name));
+    fs::rename(&resolved, &holding).map_err(io_error(&resolved))?;
+
+    match store.quarantine_artifact(path, &holding.display().to_string()).await {
+        Ok(Some(entry)) => Ok(entry),
+        outcome => {
+            // Put it back so nothing is held without a record
+            let _ = fs::rename(&holding, &resolved);
+            Err(match outcome {
+                Err(e) => QuarantineError::Store(e),
+                _ => QuarantineError::NotRecorded { path: path.to_string() },
+            })
+        }
+    }
+}
+
+/// Moves a quarantined artifact back to its original path and records it again.
+pub async fn restore(store: &dyn ArtifactStore, entry: &QuarantineEntry) -> Result<(), QuarantineError> {
+    let original = Path::new(&entry.original_path);
+    if !is_gone(original) {
+        return Err(QuarantineError::Occupied { path: entry.original_path.clone() });
+    }
+    let io_error = |source| QuarantineError::Io { path: entry.holding_path.clone(), source };
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    fs::rename(&entry.holding_path, original).map_err(io_error)?;
+
+    store.forget_quarantined(entry.id).await?;
+    let size = DiskUsage { apparent_bytes: entry.size_bytes, disk_bytes: entry.disk_bytes.unwrap_or(entry.size_bytes) };
+    store
+        .record_observation(&entry.project_path, &entry.language, &entry.original_path, size, entry.verified, entry.description.as_deref())
+        .await?;
+    Ok(())
+}
+
+/// Deletes quarantined artifacts held for more than `grace_days` and forgets them.
+/// Removed ones are listed by their original paths, leftovers by their holding paths.
+pub async fn purge_expired(store: &dyn ArtifactStore, grace_days: u32, guard: &PathGuard) -> Result<PurgeSummary, sqlx::Error> {
+    let mut summary = PurgeSummary::default();
+    for entry in store.expired_quarantine(grace_days).await? {
+        let outcome = remove_guarded(&entry.holding_path, guard, &mut summary);
+        let reason = format!("quarantined for more than {} days", grace_days);
+        let deletion = DeletionRecord {
+            size_bytes: entry.size_bytes,
+            disk_bytes: entry.disk_bytes,
+            ..audit::deletion(&entry.original_path, None, TRIGGER_QUARANTINE_EXPIRY, &outcome, Some(&reason))
+        };
+        store.record_deletion(&deletion).await?;
+        if !outcome.is_gone() {
+            continue;
+        }
+        store.forget_quarantined(entry.id).await?;
+        summary.removed.push(entry.original_path);
+    }
+    Ok(summary)
+}
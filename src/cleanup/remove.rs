@@ -0,0 +1,220 @@
+// Native removal
+// Deletes artifact trees in-process and escalates only the paths we are denied
+
+use crate::utils::format_size;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+
+/// How many leftover paths a summary lists before eliding the rest.
+const LISTED_FAILURES: usize = 5;
+
+/// One path that could not be removed.
+#[derive(Debug)]
+pub struct RemovalFailure {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// Why paths denied to us were not removed with sudo either.
+#[derive(Debug)]
+pub enum EscalationError {
+    /// sudo could not be started, e.g. it is not installed.
+    Unavailable(io::Error),
+    /// sudo ran but failed; without a password this usually means one is needed.
+    Failed(ExitStatus),
+}
+
+impl fmt::Display for EscalationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscalationError::Unavailable(e) => write!(f, "sudo is not available ({})", e),
+            EscalationError::Failed(status) => write!(f, "sudo failed ({})", status),
+        }
+    }
+}
+
+impl std::error::Error for EscalationError {}
+
+/// What removing one or more trees did, and what was left behind.
+#[derive(Debug, Default)]
+pub struct RemovalReport {
+    pub removed_files: u64,
+    pub removed_dirs: u64,
+    /// Apparent size of the files removed in-process.
+    pub removed_bytes: u64,
+    /// Paths removed with sudo after being denied to us.
+    pub escalated: Vec<PathBuf>,
+    pub failures: Vec<RemovalFailure>,
+    pub escalation_error: Option<EscalationError>,
+}
+
+impl RemovalReport {
+    /// Whether nothing was left behind.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Leftover paths that failed only for lack of permission.
+    pub fn permission_denied(&self) -> Vec<PathBuf> {
+        self.failures
+            .iter()
+            .filter(|failure| failure.error.kind() == io::ErrorKind::PermissionDenied)
+            .map(|failure| failure.path.clone())
+            .collect()
+    }
+
+    /// Whether asking for a sudo password could still finish the job.
+    pub fn needs_password(&self) -> bool {
+        !self.permission_denied().is_empty() && !matches!(self.escalation_error, Some(EscalationError::Unavailable(_)))
+    }
+
+    /// Adds another report's counts and leftovers to this one.
+    pub fn merge(&mut self, other: RemovalReport) {
+        self.removed_files += other.removed_files;
+        self.removed_dirs += other.removed_dirs;
+        self.removed_bytes += other.removed_bytes;
+        self.escalated.extend(other.escalated);
+        self.failures.extend(other.failures);
+        if other.escalation_error.is_some() {
+            self.escalation_error = other.escalation_error;
+        }
+    }
+
+    /// A few lines saying what was removed and what was left.
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "Removed {} files and {} directories ({}).",
+            self.removed_files,
+            self.removed_dirs,
+            format_size(self.removed_bytes)
+        )];
+        if !self.escalated.is_empty() {
+            lines.push(format!("{} paths removed with sudo.", self.escalated.len()));
+        }
+        if !self.failures.is_empty() {
+            lines.push(format!("{} paths left:", self.failures.len()));
+            for failure in self.failures.iter().take(LISTED_FAILURES) {
+                lines.push(format!("  {}: {}", failure.path.display(), failure.error));
+            }
+            if self.failures.len() > LISTED_FAILURES {
+                lines.push(format!("  ... and {} more", self.failures.len() - LISTED_FAILURES));
+            }
+        }
+        if let Some(error) = &self.escalation_error {
+            lines.push(format!("Could not escalate: {}.", error));
+        }
+        lines.join("\n")
+    }
+
+    fn fail(&mut self, path: &Path, error: io::Error) {
+        self.failures.push(RemovalFailure { path: path.to_path_buf(), error });
+    }
+}
+
+/// Removes `path` and everything below it without following symlinks. Keeps
+/// going past errors, so the report lists every path that was left.
+pub fn remove_tree(path: &Path) -> RemovalReport {
+    let mut report = RemovalReport::default();
+    remove_entry(path, &mut report);
+    report
+}
+
+/// Removes `path` in-process, then retries just the paths we were denied
+/// through sudo: non-interactively without a password, with `sudo -S` otherwise.
+pub fn remove_tree_escalating(path: &Path, password: Option<&str>) -> RemovalReport {
+    let mut report = remove_tree(path);
+    let denied = report.permission_denied();
+    if denied.is_empty() {
+        return report;
+    }
+    match remove_with_sudo(&denied, password) {
+        Ok(()) => {
+            // Directories that held the denied paths can go now
+            let retry = remove_tree(path);
+            report.failures = vec![];
+            report.escalated = denied;
+            report.merge(retry);
+        }
+        Err(e) => report.escalation_error = Some(e),
+    }
+    report
+}
+
+fn remove_entry(path: &Path, report: &mut RemovalReport) -> bool {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return true,
+        Err(e) => {
+            report.fail(path, e);
+            return false;
+        }
+    };
+    if !metadata.is_dir() {
+        return match fs::remove_file(path) {
+            Ok(()) => {
+                report.removed_files += 1;
+                report.removed_bytes += metadata.len();
+                true
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+            Err(e) => {
+                report.fail(path, e);
+                false
+            }
+        };
+    }
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            report.fail(path, e);
+            return false;
+        }
+    };
+    let mut emptied = true;
+    for entry in entries {
+        match entry {
+            Ok(entry) => emptied &= remove_entry(&entry.path(), report),
+            Err(e) => {
+                report.fail(path, e);
+                emptied = false;
+            }
+        }
+    }
+    // A directory with leftovers is reported through them, not as a failure of its own
+    if !emptied {
+        return false;
+    }
+    match fs::remove_dir(path) {
+        Ok(()) => {
+            report.removed_dirs += 1;
+            true
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+        Err(e) => {
+            report.fail(path, e);
+            false
+        }
+    }
+}
+
+fn remove_with_sudo(paths: &[PathBuf], password: Option<&str>) -> Result<(), EscalationError> {
+    let mut cmd = Command::new("sudo");
+    match password {
+        Some(_) => cmd.args(["-S", "-p", ""]).stdin(Stdio::piped()),
+        None => cmd.arg("-n").stdin(Stdio::null()),
+    };
+    cmd.args(["rm", "-rf", "--"]).args(paths).stdout(Stdio::null()).stderr(Stdio::null());
+    let mut child = cmd.spawn().map_err(EscalationError::Unavailable)?;
+    if let Some(password) = password
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        // sudo reports a wrong password through its exit status
+        let _ = writeln!(stdin, "{}", password);
+    }
+    let status = child.wait().map_err(EscalationError::Unavailable)?;
+    if status.success() { Ok(()) } else { Err(EscalationError::Failed(status)) }
+}
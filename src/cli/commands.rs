@@ -0,0 +1,209 @@
+// Headless command implementations
+// Progress goes to stderr so stdout stays clean for pipes
+
+use crate::cleanup;
+use crate::cli::Command;
+use crate::cleanup::plan::DeletionPlan;
+use crate::cleanup::policy::RetentionPolicy;
+use crate::cli::output::{
+    ArtifactRow, LanguageStats, OutputFormat, Plan, Stats, write_activity, write_artifacts, write_deletions, write_pins, write_plan, write_runs,
+    write_stats,
+};
+use crate::cli::daemon;
+use crate::config::Config;
+use crate::db::store::{ArtifactRecord, Pin};
+use crate::rules::RuleSet;
+use crate::scanner;
+use crate::tracking::logger::BuildLogger;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::io::stdout;
+use std::path::Path;
+use chrono::Utc;
+
+pub async fn run(command: Command, format: OutputFormat, config: Config) -> Result<(), Box<dyn Error>> {
+    let logger = BuildLogger::new(&config.database_url).await?;
+    match command {
+        Command::Scan { paths } => {
+            let scan_paths = if paths.is_empty() { config.scan_paths.clone() } else { paths };
+            let roots = scanner::ScanRoot::from_config(&scan_paths, &config);
+            let rules = RuleSet::new(&config.rules);
+            scan(&logger, format, roots, config.excluded_paths.clone(), rules).await
+        }
+        Command::List => list(&logger, format).await,
+        Command::Clean { older_than, dry_run: true } => {
+            let rules = RuleSet::new(&config.rules);
+            let plan = DeletionPlan::retention(logger.store.as_ref(), &policy_for(&config, older_than), &rules).await?;
+            write_plan(&mut stdout(), format, &Plan::from(&plan))?;
+            Ok(())
+        }
+        Command::Clean { older_than, dry_run: false } => clean(&logger, &policy_for(&config, older_than), &config).await,
+        Command::Stats => stats(&logger, format).await,
+        Command::Pin { path, note, days } => {
+            let pin = Pin::new(artifact_path(&path), note, days, Utc::now());
+            logger.store.pin(&pin).await?;
+            match pin.expires_at {
+                Some(expires_at) => eprintln!("Pinned {} until {}.", pin.path, expires_at.format("%Y-%m-%d %H:%M")),
+                None => eprintln!("Pinned {}.", pin.path),
+            }
+            Ok(())
+        }
+        Command::Unpin { path } => {
+            let path = artifact_path(&path);
+            if logger.store.unpin(&path).await? == 0 {
+                return Err(format!("{} is not pinned", path).into());
+            }
+            eprintln!("Unpinned {}.", path);
+            Ok(())
+        }
+        Command::Pins => {
+            write_pins(&mut stdout(), format, &logger.store.pins().await?)?;
+            Ok(())
+        }
+        Command::Daemon { once } => daemon::run(&logger, &config, format, once).await,
+        Command::Runs { limit } => {
+            write_runs(&mut stdout(), format, &logger.store.daemon_runs(limit).await?)?;
+            Ok(())
+        }
+        Command::Activity { limit } => {
+            write_activity(&mut stdout(), format, &logger.store.build_activity(limit).await?)?;
+            Ok(())
+        }
+        Command::Deletions { path, limit } => {
+            let deletions = logger.store.deletions(path.as_deref().map(|path| path.trim_end_matches('/')), limit).await?;
+            write_deletions(&mut stdout(), format, &deletions)?;
+            Ok(())
+        }
+    }
+}
+
+async fn scan(
+    logger: &BuildLogger,
+    format: OutputFormat,
+    roots: Vec<scanner::ScanRoot>,
+    excluded_paths: Vec<String>,
+    rules: RuleSet,
+) -> Result<(), Box<dyn Error>> {
+    let (found, failures) = scan_and_record(logger, roots, excluded_paths, rules, |line| eprintln!("{}", line)).await?;
+    let found_paths: HashSet<&str> = found.iter().map(|a| a.path.as_str()).collect();
+    let rows: Vec<ArtifactRow> = logger
+        .store
+        .current_artifacts()
+        .await?
+        .iter()
+        .filter(|record| found_paths.contains(record.path.as_str()))
+        .map(ArtifactRow::from)
+        .collect();
+    write_artifacts(&mut stdout(), format, &rows)?;
+    if failures > 0 {
+        return Err(format!("{} artifacts could not be recorded", failures).into());
+    }
+    Ok(())
+}
+
+/// Scans `roots` and records what was found. Returns the artifacts and how
+/// many of them could not be recorded.
+pub(super) async fn scan_and_record(
+    logger: &BuildLogger,
+    roots: Vec<scanner::ScanRoot>,
+    excluded_paths: Vec<String>,
+    rules: RuleSet,
+    on_log: impl Fn(String) + Send + Sync + 'static,
+) -> Result<(Vec<scanner::FoundArtifact>, usize), Box<dyn Error>> {
+    let cache = scanner::SizeCache::load(logger.store.as_ref()).await;
+    let (found, cache) = tokio::task::spawn_blocking(move || {
+        let progress = scanner::ScanProgress::new();
+        let found = scanner::scan(&roots, &excluded_paths, &rules, &cache, &progress, on_log);
+        (found, cache)
+    })
+    .await?;
+    let failures = scanner::record(logger, &found).await;
+    if let Err(e) = cache.save(logger.store.as_ref()).await {
+        eprintln!("Could not save the size cache: {}", e);
+    }
+    Ok((found, failures))
+}
+
+async fn list(logger: &BuildLogger, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let rows: Vec<ArtifactRow> = logger.store.current_artifacts().await?.iter().map(ArtifactRow::from).collect();
+    write_artifacts(&mut stdout(), format, &rows)?;
+    Ok(())
+}
+
+/// `path` made absolute the way scans record artifacts, without a trailing slash.
+fn artifact_path(path: &str) -> String {
+    let path = std::path::absolute(Path::new(path)).map(|path| path.display().to_string()).unwrap_or_else(|_| path.to_string());
+    match path.trim_end_matches('/') {
+        "" => path,
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// The configured policy, with `--older-than` in place of retention_days.
+fn policy_for(config: &Config, older_than: Option<u32>) -> RetentionPolicy {
+    let mut policy = RetentionPolicy::from_config(config);
+    if let Some(days) = older_than {
+        policy.retention_days = days;
+    }
+    policy
+}
+
+async fn clean(logger: &BuildLogger, policy: &RetentionPolicy, config: &Config) -> Result<(), Box<dyn Error>> {
+    let (summary, expired) = cleanup::purge_locked(&logger.store, policy, config, "clean").await?;
+    for path in &summary.removed {
+        println!("removed  {}", path);
+    }
+    for path in &summary.quarantined {
+        println!("quarantined {}", path);
+    }
+    for path in &expired.removed {
+        println!("purged   {}", path);
+    }
+    for path in summary.failed.iter().chain(&expired.failed) {
+        println!("failed   {}", path);
+    }
+    for refusal in summary.refused.iter().chain(&expired.refused) {
+        println!("refused  {}", refusal.path());
+        eprintln!("{}", refusal);
+    }
+    eprintln!("Removed {} artifacts.", summary.removed.len());
+    if !summary.quarantined.is_empty() {
+        eprintln!("Quarantined {} artifacts for {} days.", summary.quarantined.len(), config.quarantine_days);
+    }
+    if !expired.removed.is_empty() {
+        eprintln!("Purged {} artifacts from quarantine.", expired.removed.len());
+    }
+    let mut total = summary.total;
+    total.merge(expired.total);
+    eprintln!("{}", total.summary());
+    let failed = summary.failed.len() + expired.failed.len();
+    if failed > 0 {
+        return Err(format!("{} artifacts could not be removed", failed).into());
+    }
+    Ok(())
+}
+
+async fn stats(logger: &BuildLogger, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let artifacts = logger.store.current_artifacts().await?;
+    // Artifacts not rescanned since on-disk sizes were added count at their apparent size
+    let disk_bytes = |artifact: &ArtifactRecord| artifact.disk_bytes.unwrap_or(artifact.size_bytes);
+    let mut by_language: BTreeMap<String, (usize, u64, u64)> = BTreeMap::new();
+    for artifact in &artifacts {
+        let entry = by_language.entry(artifact.language.clone()).or_default();
+        entry.0 += 1;
+        entry.1 += artifact.size_bytes;
+        entry.2 += disk_bytes(artifact);
+    }
+    let stats = Stats {
+        artifacts: artifacts.len(),
+        total_size_bytes: artifacts.iter().map(|a| a.size_bytes).sum(),
+        total_disk_bytes: artifacts.iter().map(disk_bytes).sum(),
+        build_events: logger.store.total_builds().await?,
+        languages: by_language
+            .into_iter()
+            .map(|(language, (artifacts, size_bytes, disk_bytes))| LanguageStats { language, artifacts, size_bytes, disk_bytes })
+            .collect(),
+    };
+    write_stats(&mut stdout(), format, &stats)?;
+    Ok(())
+}
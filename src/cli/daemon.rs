@@ -0,0 +1,145 @@
+// Background cleanup daemon
+// Rescans and applies the retention policy on a schedule, under systemd or cron
+
+use crate::cleanup;
+use crate::cleanup::lock::LockError;
+use crate::cleanup::policy::RetentionPolicy;
+use crate::cli::commands::scan_and_record;
+use crate::cli::output::{OutputFormat, write_run_stream, write_run_stream_header, write_runs};
+use crate::config::Config;
+use crate::db::store::DaemonRun;
+use crate::rules::RuleSet;
+use crate::scanner::ScanRoot;
+use crate::tracking::logger::BuildLogger;
+use crate::utils::logger::log_to_file;
+use chrono::Utc;
+use std::error::Error;
+use std::io::stdout;
+use std::slice;
+
+pub const STATUS_COMPLETED: &str = "completed";
+/// Another cleanup held the lock, so nothing was deleted.
+pub const STATUS_SKIPPED: &str = "skipped";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Makes a pass every `daemon_interval`, or just one with `once`. Each pass
+/// is recorded in the database and printed in `format`: like `ratifact runs`
+/// with `once`, otherwise as a stream with one JSON line or CSV row per pass. SIGTERM or Ctrl+C stop the
+/// daemon once the pass in progress is done.
+pub async fn run(logger: &BuildLogger, config: &Config, format: OutputFormat, once: bool) -> Result<(), Box<dyn Error>> {
+    if once {
+        let run = pass(logger, config).await;
+        write_runs(&mut stdout(), format, slice::from_ref(&run))?;
+        logger.store.record_daemon_run(&run).await?;
+        if run.status == STATUS_FAILED {
+            return Err(run.detail.unwrap_or_default().into());
+        }
+        return Ok(());
+    }
+    let mut shutdown = Shutdown::new()?;
+    eprintln!("ratifact daemon: a pass every {}, scanning {}", config.daemon_interval, config.scan_paths.join(", "));
+    write_run_stream_header(&mut stdout(), format)?;
+    loop {
+        let run = pass(logger, config).await;
+        write_run_stream(&mut stdout(), format, &run)?;
+        // A database hiccup should not stop the schedule
+        if let Err(e) = logger.store.record_daemon_run(&run).await {
+            eprintln!("Could not record the pass: {}", e);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(config.daemon_period()) => {}
+            _ = shutdown.wait() => {
+                eprintln!("ratifact daemon: stopping");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Rescans the configured paths, then deletes what the retention policy
+/// picks and purges expired quarantine.
+pub async fn pass(logger: &BuildLogger, config: &Config) -> DaemonRun {
+    let started_at = Utc::now();
+    let mut run = DaemonRun {
+        started_at,
+        finished_at: started_at,
+        status: STATUS_COMPLETED.to_string(),
+        artifacts: 0,
+        removed: 0,
+        quarantined: 0,
+        purged: 0,
+        failed: 0,
+        refused: 0,
+        freed_bytes: 0,
+        detail: None,
+    };
+    if let Err(e) = cleanup_pass(logger, config, &mut run).await {
+        run.status = STATUS_FAILED.to_string();
+        run.detail = Some(e.to_string());
+    }
+    run.finished_at = Utc::now();
+    run
+}
+
+async fn cleanup_pass(logger: &BuildLogger, config: &Config, run: &mut DaemonRun) -> Result<(), Box<dyn Error>> {
+    let roots = ScanRoot::from_config(&config.scan_paths, config);
+    let rules = RuleSet::new(&config.rules);
+    let (found, failures) =
+        scan_and_record(logger, roots, config.excluded_paths.clone(), rules, |line| log_to_file(&line)).await?;
+    run.artifacts = found.len() as u64;
+    if failures > 0 {
+        run.detail = Some(format!("{} artifacts could not be recorded", failures));
+    }
+
+    let policy = RetentionPolicy::from_config(config);
+    let (summary, expired) = match cleanup::purge_locked(&logger.store, &policy, config, "daemon").await {
+        Ok(summaries) => summaries,
+        Err(e @ LockError::Busy(_)) => {
+            run.status = STATUS_SKIPPED.to_string();
+            run.detail = Some(e.to_string());
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    for refusal in summary.refused.iter().chain(&expired.refused) {
+        log_to_file(&format!("Daemon cleanup: {}", refusal));
+    }
+    run.removed = summary.removed.len() as u64;
+    run.quarantined = summary.quarantined.len() as u64;
+    run.purged = expired.removed.len() as u64;
+    run.failed = (summary.failed.len() + expired.failed.len()) as u64;
+    run.refused = (summary.refused.len() + expired.refused.len()) as u64;
+    run.freed_bytes = summary.total.removed_bytes + expired.total.removed_bytes;
+    Ok(())
+}
+
+/// SIGTERM and SIGINT, listened for from startup so a signal that arrives
+/// during a pass is still seen once it is done.
+struct Shutdown {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+}
+
+impl Shutdown {
+    fn new() -> std::io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            Ok(Shutdown { terminate: signal(SignalKind::terminate())?, interrupt: signal(SignalKind::interrupt())? })
+        }
+        #[cfg(not(unix))]
+        Ok(Shutdown {})
+    }
+
+    async fn wait(&mut self) {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = self.terminate.recv() => {}
+            _ = self.interrupt.recv() => {}
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
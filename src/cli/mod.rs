@@ -0,0 +1,190 @@
+// Command-line interface
+// Headless subcommands for scripts, cron and SSH sessions without a TTY
+
+pub mod commands;
+pub mod daemon;
+pub mod output;
+
+use crate::config::PartialConfig;
+use crate::config::settings::{parse_policies, parse_rules, parse_scan_depths};
+use crate::cleanup::policy::PolicyBlock;
+use crate::rules::Rule;
+use crate::utils::parse_age;
+use clap::{Parser, Subcommand};
+use output::OutputFormat;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "ratifact", version, about = "Track and manage build artifacts")]
+pub struct Cli {
+    /// Run a headless command instead of the TUI
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Output format for scan, list, stats, pins, runs, deletions and clean --dry-run
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Read and save settings from this file instead of the XDG config locations
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Database URL, overriding the config file and environment
+    #[arg(long, global = true, value_name = "URL")]
+    pub database_url: Option<String>,
+
+    /// Path to scan; repeat for several. Overrides scan_paths
+    #[arg(long = "scan-path", global = true, value_name = "PATH")]
+    pub scan_paths: Vec<String>,
+
+    /// Retention period such as 30d or 4w. Overrides retention_days
+    #[arg(long, global = true, value_parser = parse_age, value_name = "AGE")]
+    pub retention_days: Option<u32>,
+
+    /// Write debug logs (true or false)
+    #[arg(long, global = true, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub debug_logs: Option<bool>,
+
+    /// Path to skip while scanning; repeat for several. Overrides excluded_paths
+    #[arg(long = "exclude-path", global = true, value_name = "PATH")]
+    pub excluded_paths: Vec<String>,
+
+    /// Directory levels to search below each scan path. Overrides max_depth
+    #[arg(long, global = true, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Per-path search depths as a TOML inline table, such as '{ "/home/me/code" = 8 }'. Overrides scan_depths
+    #[arg(long, global = true, value_parser = parse_scan_depths, value_name = "TOML")]
+    pub scan_depths: Option<BTreeMap<String, usize>>,
+
+    /// Artifact rules as a TOML array of inline tables. Overrides rules
+    #[arg(long, global = true, value_parser = parse_rules, value_name = "TOML")]
+    pub rules: Option<Vec<Rule>>,
+
+    /// Policy blocks as a TOML array of inline tables. Overrides policies
+    #[arg(long, global = true, value_parser = parse_policies, value_name = "TOML")]
+    pub policies: Option<Vec<PolicyBlock>>,
+
+    /// Descend into symlinked directories while scanning (true or false)
+    #[arg(long, global = true, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub follow_symlinks: Option<bool>,
+
+    /// Stay on the filesystem of each scan path (true or false)
+    #[arg(long, global = true, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub one_file_system: Option<bool>,
+
+    /// Move deleted artifacts into quarantine instead of removing them (true or false)
+    #[arg(long, global = true, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub quarantine: Option<bool>,
+
+    /// How long quarantined artifacts can be restored, such as 7d or 2w. Overrides quarantine_days
+    #[arg(long, global = true, value_parser = parse_age, value_name = "AGE")]
+    pub quarantine_days: Option<u32>,
+
+    /// Keep all artifacts together under this size, such as 50GB. Overrides max_total_size
+    #[arg(long, global = true, value_name = "SIZE")]
+    pub max_total_size: Option<String>,
+
+    /// Delete least recently used artifacts while a filesystem has less free space, in percent. Overrides min_free_percent
+    #[arg(long, global = true, value_name = "PERCENT")]
+    pub min_free_percent: Option<u8>,
+
+    /// Keep at most this many artifacts per project. Overrides max_artifacts_per_project
+    #[arg(long, global = true, value_name = "N")]
+    pub max_artifacts_per_project: Option<usize>,
+
+    /// Time between daemon passes, such as 6h or 1d. Overrides daemon_interval
+    #[arg(long, global = true, value_name = "INTERVAL")]
+    pub daemon_interval: Option<String>,
+}
+
+impl Cli {
+    /// The config layer set by command-line flags.
+    pub fn config_overrides(&self) -> PartialConfig {
+        let list = |values: &Vec<String>| Some(values.clone()).filter(|values| !values.is_empty());
+        PartialConfig {
+            database_url: self.database_url.clone(),
+            scan_paths: list(&self.scan_paths),
+            retention_days: self.retention_days,
+            debug_logs_enabled: self.debug_logs,
+            excluded_paths: list(&self.excluded_paths),
+            rules: self.rules.clone(),
+            max_depth: self.max_depth,
+            scan_depths: self.scan_depths.clone(),
+            follow_symlinks: self.follow_symlinks,
+            one_file_system: self.one_file_system,
+            quarantine: self.quarantine,
+            quarantine_days: self.quarantine_days,
+            max_total_size: self.max_total_size.clone(),
+            min_free_percent: self.min_free_percent,
+            max_artifacts_per_project: self.max_artifacts_per_project,
+            policies: self.policies.clone(),
+            daemon_interval: self.daemon_interval.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Scan the configured paths and record the artifacts found
+    Scan {
+        /// Scan these paths instead of the configured scan_paths
+        #[arg(long = "path")]
+        paths: Vec<String>,
+    },
+    /// List known artifacts
+    List,
+    /// Delete artifacts unused for the retention period or beyond the cleanup limits
+    Clean {
+        /// Time unused such as 14d or 2w; defaults to retention_days from the config
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<u32>,
+        /// Print what would be deleted and how much space it frees, without deleting
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show artifact and build totals
+    Stats,
+    /// Pin an artifact so bulk deletion and retention skip it
+    Pin {
+        path: String,
+        /// Why the artifact is kept
+        #[arg(long)]
+        note: Option<String>,
+        /// Let the pin lapse after a time such as 14d or 2w
+        #[arg(long = "for", value_parser = parse_age, value_name = "AGE")]
+        days: Option<u32>,
+    },
+    /// Remove the pin of an artifact
+    Unpin { path: String },
+    /// List pinned artifacts, expired pins included
+    Pins,
+    /// Rescan and apply the retention policy every daemon_interval until stopped
+    Daemon {
+        /// Make a single pass and exit, for cron
+        #[arg(long)]
+        once: bool,
+    },
+    /// Show the daemon's passes, newest first
+    Runs {
+        /// Show at most this many passes
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Show build activity the TUI's watcher recorded, newest first
+    Activity {
+        /// Show at most this many bursts
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Show the deletion audit log, newest first
+    Deletions {
+        /// Only deletions of this path or of paths below it
+        #[arg(long)]
+        path: Option<String>,
+        /// Show at most this many entries
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+}
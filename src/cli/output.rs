@@ -0,0 +1,434 @@
+// Structured output for headless commands
+// JSON and CSV follow a versioned schema documented in the README
+
+use crate::cleanup::plan::{DeletionPlan, PlannedArtifact};
+use crate::db::store::{ArtifactRecord, BuildActivity, DaemonRun, DeletionRecord, Pin};
+use crate::utils::{format_size, format_size_change};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Bumped whenever a field is renamed or removed. New fields may be added
+/// without a bump; CSV columns are only ever appended.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactRow {
+    pub path: String,
+    pub project_path: String,
+    pub language: String,
+    pub size_bytes: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub verified: bool,
+    pub description: Option<String>,
+    pub disk_bytes: Option<u64>,
+    /// Whether an unexpired pin keeps the artifact out of bulk deletion and retention.
+    pub pinned: bool,
+    pub pin_note: Option<String>,
+    pub pin_expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<&ArtifactRecord> for ArtifactRow {
+    fn from(record: &ArtifactRecord) -> Self {
+        let last_modified = std::fs::metadata(&record.path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
+        let pin = record.pin.as_ref().filter(|pin| pin.is_active(Utc::now()));
+        ArtifactRow {
+            path: record.path.clone(),
+            project_path: record.project_path.clone(),
+            language: record.language.clone(),
+            size_bytes: record.size_bytes,
+            last_modified,
+            first_seen: record.first_seen,
+            last_seen: record.last_seen,
+            verified: record.verified,
+            description: record.description.clone(),
+            disk_bytes: record.disk_bytes,
+            pinned: pin.is_some(),
+            pin_note: pin.and_then(|pin| pin.note.clone()),
+            pin_expires_at: pin.and_then(|pin| pin.expires_at),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub artifacts: usize,
+    pub size_bytes: u64,
+    pub disk_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub artifacts: usize,
+    pub total_size_bytes: u64,
+    /// Allocated size, what deleting every artifact would free.
+    pub total_disk_bytes: u64,
+    pub build_events: u64,
+    pub languages: Vec<LanguageStats>,
+}
+
+/// What `clean --dry-run` would delete.
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    pub artifacts: Vec<PlannedArtifact>,
+    pub total_size_bytes: u64,
+    pub total_disk_bytes: u64,
+    /// Pinned artifacts the plan leaves alone.
+    pub pinned: usize,
+}
+
+impl From<&DeletionPlan> for Plan {
+    fn from(plan: &DeletionPlan) -> Self {
+        let (total_size_bytes, total_disk_bytes) = plan.selected_bytes();
+        Plan { artifacts: plan.selected().cloned().collect(), total_size_bytes, total_disk_bytes, pinned: plan.pinned }
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, T: Serialize> {
+    schema_version: u32,
+    kind: &'a str,
+    data: T,
+}
+
+fn write_json<T: Serialize>(out: &mut impl Write, kind: &str, data: T) -> io::Result<()> {
+    let envelope = Envelope { schema_version: SCHEMA_VERSION, kind, data };
+    serde_json::to_writer_pretty(&mut *out, &envelope)?;
+    writeln!(out)
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+pub fn write_artifacts(out: &mut impl Write, format: OutputFormat, rows: &[ArtifactRow]) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            writeln!(
+                out,
+                "{:>10}  {:>10}  {:<12}  {:<8}  {:<16}  {:<16}  PATH",
+                "SIZE", "ON DISK", "LANGUAGE", "VERIFIED", "LAST MODIFIED", "FIRST SEEN"
+            )?;
+            for row in rows {
+                writeln!(
+                    out,
+                    "{:>10}  {:>10}  {:<12}  {:<8}  {:<16}  {:<16}  {}",
+                    format_size(row.size_bytes),
+                    row.disk_bytes.map(format_size).unwrap_or_else(|| "-".to_string()),
+                    row.language,
+                    if row.verified { "yes" } else { "no" },
+                    format_time(row.last_modified),
+                    format_time(Some(row.first_seen)),
+                    row.path
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "artifacts", rows),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            if rows.is_empty() {
+                writer.write_record([
+                    "path", "project_path", "language", "size_bytes", "last_modified", "first_seen", "last_seen", "verified",
+                    "description", "disk_bytes", "pinned", "pin_note", "pin_expires_at",
+                ])?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+pub fn write_stats(out: &mut impl Write, format: OutputFormat, stats: &Stats) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            writeln!(out, "Artifacts:    {}", stats.artifacts)?;
+            writeln!(out, "Total size:   {}", format_size(stats.total_size_bytes))?;
+            writeln!(out, "On disk:      {}", format_size(stats.total_disk_bytes))?;
+            writeln!(out, "Build events: {}", stats.build_events)?;
+            writeln!(out)?;
+            writeln!(out, "{:<12}  {:>9}  {:>10}  {:>10}", "LANGUAGE", "ARTIFACTS", "SIZE", "ON DISK")?;
+            for language in &stats.languages {
+                writeln!(
+                    out,
+                    "{:<12}  {:>9}  {:>10}  {:>10}",
+                    language.language,
+                    language.artifacts,
+                    format_size(language.size_bytes),
+                    format_size(language.disk_bytes)
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "stats", stats),
+        OutputFormat::Csv => {
+            // One row per language; totals are the column sums
+            let mut writer = csv::Writer::from_writer(out);
+            for language in &stats.languages {
+                writer.serialize(language)?;
+            }
+            if stats.languages.is_empty() {
+                writer.write_record(["language", "artifacts", "size_bytes", "disk_bytes"])?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+pub fn write_plan(out: &mut impl Write, format: OutputFormat, plan: &Plan) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            writeln!(
+                out,
+                "{:>10}  {:>10}  {:>5}  {:<12}  {:<24}  {:<12}  PATH",
+                "SIZE", "ON DISK", "IDLE", "LANGUAGE", "RULE", "POLICY"
+            )?;
+            for artifact in &plan.artifacts {
+                writeln!(
+                    out,
+                    "{:>10}  {:>10}  {:>5}  {:<12}  {:<24}  {:<12}  {}",
+                    format_size(artifact.size_bytes),
+                    format_size(artifact.disk_bytes),
+                    format!("{}d", artifact.idle_days),
+                    artifact.language,
+                    artifact.rule,
+                    artifact.policy.as_deref().unwrap_or("default"),
+                    artifact.path
+                )?;
+            }
+            writeln!(
+                out,
+                "{} artifacts, {} to free ({} on disk)",
+                plan.artifacts.len(),
+                format_size(plan.total_size_bytes),
+                format_size(plan.total_disk_bytes)
+            )?;
+            if plan.pinned > 0 {
+                writeln!(out, "{} pinned artifacts kept", plan.pinned)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "plan", plan),
+        OutputFormat::Csv => {
+            // One row per artifact; totals are the column sums
+            let mut writer = csv::Writer::from_writer(out);
+            for artifact in &plan.artifacts {
+                writer.serialize(artifact)?;
+            }
+            if plan.artifacts.is_empty() {
+                writer.write_record([
+                    "path",
+                    "project_path",
+                    "language",
+                    "size_bytes",
+                    "disk_bytes",
+                    "first_seen",
+                    "age_days",
+                    "rule",
+                    "verified",
+                    "last_used",
+                    "last_used_signal",
+                    "idle_days",
+                    "reason",
+                    "policy",
+                ])?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+pub fn write_deletions(out: &mut impl Write, format: OutputFormat, deletions: &[DeletionRecord]) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            writeln!(out, "{:<16}  {:<12}  {:<17}  {:<11}  {:>10}  PATH", "WHEN", "USER", "TRIGGER", "OUTCOME", "ON DISK")?;
+            for deletion in deletions {
+                let detail = deletion
+                    .detail
+                    .iter()
+                    .chain(&deletion.reason)
+                    .map(|note| format!(" ({})", note))
+                    .collect::<String>();
+                writeln!(
+                    out,
+                    "{:<16}  {:<12}  {:<17}  {:<11}  {:>10}  {}{}",
+                    format_time(Some(deletion.deleted_at)),
+                    deletion.user,
+                    deletion.trigger,
+                    deletion.outcome,
+                    format_size(deletion.disk_bytes.unwrap_or(deletion.size_bytes)),
+                    deletion.path,
+                    detail
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "deletions", deletions),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for deletion in deletions {
+                writer.serialize(deletion)?;
+            }
+            if deletions.is_empty() {
+                writer.write_record([
+                    "path", "size_bytes", "disk_bytes", "trigger", "user", "deleted_at", "outcome", "detail", "reason",
+                ])?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+pub fn write_pins(out: &mut impl Write, format: OutputFormat, pins: &[Pin]) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            let now = Utc::now();
+            writeln!(out, "{:<16}  {:<16}  PATH", "PINNED", "EXPIRES")?;
+            for pin in pins {
+                let expires = match pin.expires_at {
+                    Some(_) if !pin.is_active(now) => "expired".to_string(),
+                    expires_at => format_time(expires_at),
+                };
+                let note = pin.note.as_ref().map(|note| format!(" ({})", note)).unwrap_or_default();
+                writeln!(out, "{:<16}  {:<16}  {}{}", format_time(Some(pin.pinned_at)), expires, pin.path, note)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "pins", pins),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for pin in pins {
+                writer.serialize(pin)?;
+            }
+            if pins.is_empty() {
+                writer.write_record(["path", "note", "pinned_at", "expires_at"])?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+/// One daemon pass for humans, e.g. "2025-01-02 03:04 completed: 12 artifacts, 2 removed, 0 quarantined, 1 purged, 1.2 GB freed".
+pub fn run_line(run: &DaemonRun) -> String {
+    let mut line = format!(
+        "{} {}: {} artifacts, {} removed, {} quarantined, {} purged, {} freed",
+        format_time(Some(run.started_at)),
+        run.status,
+        run.artifacts,
+        run.removed,
+        run.quarantined,
+        run.purged,
+        format_size(run.freed_bytes)
+    );
+    if run.failed + run.refused > 0 {
+        line.push_str(&format!(", {} failed, {} refused", run.failed, run.refused));
+    }
+    if let Some(detail) = &run.detail {
+        line.push_str(&format!(" ({})", detail));
+    }
+    line
+}
+
+pub fn write_activity(out: &mut impl Write, format: OutputFormat, activity: &[BuildActivity]) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            for burst in activity {
+                writeln!(
+                    out,
+                    "{} → {} {}: {} changes, {} (now {})",
+                    format_time(Some(burst.started_at)),
+                    burst.ended_at.format("%H:%M"),
+                    burst.artifact_path,
+                    burst.events,
+                    format_size_change(burst.bytes_changed()),
+                    format_size(burst.size_after)
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "activity", activity),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for burst in activity {
+                writer.serialize(burst)?;
+            }
+            if activity.is_empty() {
+                writer.write_record(["artifact_path", "started_at", "ended_at", "events", "size_before", "size_after"])?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+const RUN_COLUMNS: [&str; 11] = [
+    "started_at", "finished_at", "status", "artifacts", "removed", "quarantined", "purged", "failed", "refused", "freed_bytes",
+    "detail",
+];
+
+pub fn write_runs(out: &mut impl Write, format: OutputFormat, runs: &[DaemonRun]) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            for run in runs {
+                writeln!(out, "{}", run_line(run))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json(out, "runs", runs),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for run in runs {
+                writer.serialize(run)?;
+            }
+            if runs.is_empty() {
+                writer.write_record(RUN_COLUMNS)?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+/// Starts the output of a daemon that runs until stopped. Only CSV has a
+/// header, written once for all passes.
+pub fn write_run_stream_header(out: &mut impl Write, format: OutputFormat) -> io::Result<()> {
+    if format != OutputFormat::Csv {
+        return Ok(());
+    }
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(RUN_COLUMNS)?;
+    writer.flush()
+}
+
+/// One pass of a daemon that runs until stopped: a line of text, a CSV row,
+/// or for JSON a compact envelope of kind "run" on its own line (JSON Lines).
+pub fn write_run_stream(out: &mut impl Write, format: OutputFormat, run: &DaemonRun) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => writeln!(out, "{}", run_line(run))?,
+        OutputFormat::Json => {
+            let envelope = Envelope { schema_version: SCHEMA_VERSION, kind: "run", data: run };
+            serde_json::to_writer(&mut *out, &envelope)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(&mut *out);
+            writer.serialize(run)?;
+            writer.flush()?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,27 @@
+// Configuration errors
+// Every error names the file or variable and the key that is wrong
+
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file exists but could not be read.
+    Read { path: PathBuf, source: std::io::Error },
+    /// The file is not valid TOML, has an unknown key or a value of the wrong type.
+    Parse { path: PathBuf, message: String },
+    /// A key holds a value ratifact cannot use.
+    Invalid { key: &'static str, origin: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => write!(f, "cannot read {}: {}", path.display(), source),
+            ConfigError::Parse { path, message } => write!(f, "invalid config file {}: {}", path.display(), message.trim_end()),
+            ConfigError::Invalid { key, origin, message } => write!(f, "invalid {} (from {}): {}", key, origin, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -1,7 +1,9 @@
 // Configuration modules
 
+pub mod error;
+pub mod paths;
 pub mod settings;
 pub mod types;
 
 // Re-export
-pub use types::Config;
+pub use types::{Config, PartialConfig};
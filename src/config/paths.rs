@@ -0,0 +1,29 @@
+// Config file locations
+// User config follows the XDG base directory spec, with a system-wide fallback
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Where older releases kept settings, relative to the working directory.
+pub const LEGACY_CONFIG_PATH: &str = "src/config/config.toml";
+
+/// System-wide config shared by every user on the machine.
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/ratifact/config.toml";
+
+/// `$XDG_CONFIG_HOME/ratifact/config.toml`, falling back to `~/.config` when unset.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("ratifact").join("config.toml"))
+}
+
+/// Config files in the order they are layered: the system-wide file first,
+/// then the `--config` override or, without one, the user file.
+pub fn config_files(override_path: Option<&Path>, user: Option<&Path>, system: &Path) -> Vec<PathBuf> {
+    let mut files = vec![system.to_path_buf()];
+    files.extend(override_path.or(user).map(Path::to_path_buf));
+    files
+}
@@ -1,55 +1,394 @@
 // Settings management
+// Layers defaults, config files, RATIFACT_* environment variables and CLI flags
 
-use crate::config::types::Config;
+use crate::config::error::ConfigError;
+use crate::config::paths::{LEGACY_CONFIG_PATH, SYSTEM_CONFIG_PATH, config_files, user_config_path};
+use crate::cleanup::policy::PolicyBlock;
+use crate::config::types::{Config, PartialConfig};
+use crate::rules::Rule;
+use crate::utils::{parse_interval, parse_size};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-pub fn load_config() -> Config {
+static CONFIG_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Loads and saves settings at `path` instead of the XDG locations.
+/// Set once at startup from `--config`.
+pub fn set_config_override(path: PathBuf) {
+    CONFIG_OVERRIDE.set(path).ok();
+}
+
+/// The file settings are saved to: the override if given, otherwise the user config.
+pub fn config_path() -> Option<PathBuf> {
+    CONFIG_OVERRIDE.get().cloned().or_else(user_config_path)
+}
+
+/// Resolves the effective config. Later layers win: defaults, the system file,
+/// the user (or `--config`) file, the environment, then `cli`.
+pub fn load_config(cli: PartialConfig) -> Result<Config, ConfigError> {
     dotenvy::dotenv().ok(); // Load .env file
-    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| {
-        let user = std::env::var("POSTGRES_USERNAME").unwrap_or_else(|_| "ratifact".to_string());
-        let password =
-            std::env::var("POSTGRES_PASSWORD").unwrap_or_else(|_| "password".to_string());
-        format!(
-            "postgres://{}:{}@localhost:25851/ratifact",
-            user, password
-        )
-    });
-
-    let debug_logs_enabled = std::env::var("DEBUG_LOGS_ENABLED")
-        .unwrap_or_else(|_| "false".to_string())
-        .to_lowercase() == "true";
-
-    // Try to load from src/config/config.toml
-    if let Ok(content) = fs::read_to_string("src/config/config.toml") {
-        if let Ok(mut config) = toml::from_str::<Config>(&content) {
-            // Override database_url and debug_logs_enabled from env
-            config.database_url = database_url;
-            config.debug_logs_enabled = debug_logs_enabled;
-            return config;
+    let override_path = CONFIG_OVERRIDE.get();
+    let user = user_config_path();
+    if override_path.is_none()
+        && let Some(user) = &user
+    {
+        migrate_legacy_config(Path::new(LEGACY_CONFIG_PATH), user).ok();
+    }
+    let files = config_files(
+        override_path.map(PathBuf::as_path),
+        user.as_deref(),
+        Path::new(SYSTEM_CONFIG_PATH),
+    );
+    let env = env_overrides(|name| std::env::var(name).ok())?;
+    load_layers(&files, env, cli)
+}
+
+/// Merges `files` (missing ones are skipped), then `env`, then `cli` on top of
+/// the defaults and validates the result.
+pub fn load_layers(files: &[PathBuf], env: PartialConfig, cli: PartialConfig) -> Result<Config, ConfigError> {
+    let mut layered = Layered::new(default_config());
+    for path in files {
+        if let Some(layer) = read_config_file(path)? {
+            layered.apply(layer, &path.display().to_string());
         }
     }
+    layered.apply(env, "environment");
+    layered.apply(cli, "command line");
+    layered.validate()?;
+    Ok(layered.config)
+}
 
-    Config {
-        database_url,
-        scan_paths: vec![".".to_string()],
-        retention_days: 30,
-        debug_logs_enabled,
-        excluded_paths: vec![],
+/// Reads one config file. Returns None when it does not exist.
+pub fn read_config_file(path: &Path) -> Result<Option<PartialConfig>, ConfigError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(source) => return Err(ConfigError::Read { path: path.to_path_buf(), source }),
+    };
+    let mut layer: PartialConfig = toml::from_str(&content)
+        .map_err(|e| ConfigError::Parse { path: path.to_path_buf(), message: e.to_string() })?;
+    // Older releases saved an empty database_url placeholder
+    if layer.database_url.as_deref() == Some("") {
+        layer.database_url = None;
     }
+    Ok(Some(layer))
 }
 
-pub fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
-    // Create a copy without database_url for saving
-    let save_config = Config {
-        database_url: "".to_string(), // Not saving db url
-        scan_paths: config.scan_paths.clone(),
-        retention_days: config.retention_days,
-        debug_logs_enabled: config.debug_logs_enabled,
-        excluded_paths: config.excluded_paths.clone(),
+/// Collects RATIFACT_* variables. `DATABASE_URL` and `DEBUG_LOGS_ENABLED`
+/// are still honored when the prefixed variable is not set.
+pub fn env_overrides(lookup: impl Fn(&str) -> Option<String>) -> Result<PartialConfig, ConfigError> {
+    let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+    let invalid = |key, name: &str, message: &str| ConfigError::Invalid {
+        key,
+        origin: name.to_string(),
+        message: message.to_string(),
     };
-    let toml_string = toml::to_string(&save_config)?;
-    fs::write("src/config/config.toml", toml_string)?;
-    Ok(())
+
+    let retention_days = parse_number(var("RATIFACT_RETENTION_DAYS"))
+        .map_err(|value| invalid("retention_days", "RATIFACT_RETENTION_DAYS", &format!("expected a number of days, got '{}'", value)))?;
+    let quarantine_days = parse_number(var("RATIFACT_QUARANTINE_DAYS"))
+        .map_err(|value| invalid("quarantine_days", "RATIFACT_QUARANTINE_DAYS", &format!("expected a number of days, got '{}'", value)))?;
+    let max_depth = parse_number(var("RATIFACT_MAX_DEPTH"))
+        .map_err(|value| invalid("max_depth", "RATIFACT_MAX_DEPTH", &format!("expected a whole number, got '{}'", value)))?;
+    let min_free_percent = parse_number(var("RATIFACT_MIN_FREE_PERCENT"))
+        .map_err(|value| invalid("min_free_percent", "RATIFACT_MIN_FREE_PERCENT", &format!("expected a percentage, got '{}'", value)))?;
+    let max_artifacts_per_project = parse_number(var("RATIFACT_MAX_ARTIFACTS_PER_PROJECT")).map_err(|value| {
+        invalid("max_artifacts_per_project", "RATIFACT_MAX_ARTIFACTS_PER_PROJECT", &format!("expected a whole number, got '{}'", value))
+    })?;
+    let flag = |key, name: &str| match var(name) {
+        Some(value) => parse_bool(&value)
+            .map(Some)
+            .ok_or_else(|| invalid(key, name, &format!("expected true or false, got '{}'", value))),
+        None => Ok(None),
+    };
+    let debug_name = if var("RATIFACT_DEBUG_LOGS_ENABLED").is_some() { "RATIFACT_DEBUG_LOGS_ENABLED" } else { "DEBUG_LOGS_ENABLED" };
+    let debug_logs_enabled = flag("debug_logs_enabled", debug_name)?;
+    let follow_symlinks = flag("follow_symlinks", "RATIFACT_FOLLOW_SYMLINKS")?;
+    let one_file_system = flag("one_file_system", "RATIFACT_ONE_FILE_SYSTEM")?;
+    let quarantine = flag("quarantine", "RATIFACT_QUARANTINE")?;
+    let rules = var("RATIFACT_RULES")
+        .map(|value| parse_rules(&value))
+        .transpose()
+        .map_err(|message| invalid("rules", "RATIFACT_RULES", &message))?;
+    let scan_depths = var("RATIFACT_SCAN_DEPTHS")
+        .map(|value| parse_scan_depths(&value))
+        .transpose()
+        .map_err(|message| invalid("scan_depths", "RATIFACT_SCAN_DEPTHS", &message))?;
+    let policies = var("RATIFACT_POLICIES")
+        .map(|value| parse_policies(&value))
+        .transpose()
+        .map_err(|message| invalid("policies", "RATIFACT_POLICIES", &message))?;
+
+    Ok(PartialConfig {
+        database_url: var("RATIFACT_DATABASE_URL").or_else(|| var("DATABASE_URL")),
+        scan_paths: var("RATIFACT_SCAN_PATHS").map(|value| split_path_list(&value)),
+        retention_days,
+        debug_logs_enabled,
+        excluded_paths: var("RATIFACT_EXCLUDED_PATHS").map(|value| split_path_list(&value)),
+        rules,
+        max_depth,
+        scan_depths,
+        follow_symlinks,
+        one_file_system,
+        quarantine,
+        quarantine_days,
+        max_total_size: var("RATIFACT_MAX_TOTAL_SIZE"),
+        min_free_percent,
+        max_artifacts_per_project,
+        policies,
+        daemon_interval: var("RATIFACT_DAEMON_INTERVAL"),
+    })
 }
 
+/// Parses a TOML value as it would appear after `key = ` in a config file,
+/// such as `{ "/home/me/code" = 8 }` or `[{ name = "zig", ... }]`.
+pub fn parse_toml_value<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    #[derive(Deserialize)]
+    struct Wrapper<T> {
+        value: T,
+    }
+    toml::from_str::<Wrapper<T>>(&format!("value = {}", value))
+        .map(|wrapper| wrapper.value)
+        .map_err(|e| format!("expected a TOML value: {}", e.message()))
+}
+
+/// `[[rules]]` as a TOML array of inline tables, for `--rules`.
+pub fn parse_rules(value: &str) -> Result<Vec<Rule>, String> {
+    parse_toml_value(value)
+}
+
+/// `[scan_depths]` as a TOML inline table, for `--scan-depths`.
+pub fn parse_scan_depths(value: &str) -> Result<BTreeMap<String, usize>, String> {
+    parse_toml_value(value)
+}
 
+/// `[[policies]]` as a TOML array of inline tables, for `--policies`.
+pub fn parse_policies(value: &str) -> Result<Vec<PolicyBlock>, String> {
+    parse_toml_value(value)
+}
+
+/// Splits a list like `$PATH` (`:` separated, `;` on Windows).
+fn split_path_list(value: &str) -> Vec<String> {
+    std::env::split_paths(value).map(|path| path.to_string_lossy().into_owned()).collect()
+}
+
+/// Parses an optional variable, handing back the raw value on failure.
+fn parse_number<T: std::str::FromStr>(value: Option<String>) -> Result<Option<T>, String> {
+    match value {
+        Some(value) => value.trim().parse().map(Some).map_err(|_| value),
+        None => Ok(None),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn default_config() -> Config {
+    let user = std::env::var("POSTGRES_USERNAME").unwrap_or_else(|_| "ratifact".to_string());
+    let password = std::env::var("POSTGRES_PASSWORD").unwrap_or_else(|_| "password".to_string());
+    Config {
+        database_url: format!("postgres://{}:{}@localhost:25851/ratifact", user, password),
+        ..Config::default()
+    }
+}
+
+/// The merged config plus where each key was last set, for error messages.
+struct Layered {
+    config: Config,
+    origins: HashMap<&'static str, String>,
+}
+
+impl Layered {
+    fn new(config: Config) -> Self {
+        Layered { config, origins: HashMap::new() }
+    }
+
+    fn apply(&mut self, layer: PartialConfig, origin: &str) {
+        let mut set = |key| {
+            self.origins.insert(key, origin.to_string());
+        };
+        if let Some(value) = layer.database_url {
+            self.config.database_url = value;
+            set("database_url");
+        }
+        if let Some(value) = layer.scan_paths {
+            self.config.scan_paths = value;
+            set("scan_paths");
+        }
+        if let Some(value) = layer.retention_days {
+            self.config.retention_days = value;
+            set("retention_days");
+        }
+        if let Some(value) = layer.debug_logs_enabled {
+            self.config.debug_logs_enabled = value;
+            set("debug_logs_enabled");
+        }
+        if let Some(value) = layer.excluded_paths {
+            self.config.excluded_paths = value;
+            set("excluded_paths");
+        }
+        if let Some(value) = layer.rules {
+            self.config.rules = value;
+            set("rules");
+        }
+        if let Some(value) = layer.max_depth {
+            self.config.max_depth = value;
+            set("max_depth");
+        }
+        if let Some(value) = layer.scan_depths {
+            self.config.scan_depths = value;
+            set("scan_depths");
+        }
+        if let Some(value) = layer.follow_symlinks {
+            self.config.follow_symlinks = value;
+            set("follow_symlinks");
+        }
+        if let Some(value) = layer.one_file_system {
+            self.config.one_file_system = value;
+            set("one_file_system");
+        }
+        if let Some(value) = layer.quarantine {
+            self.config.quarantine = value;
+            set("quarantine");
+        }
+        if let Some(value) = layer.quarantine_days {
+            self.config.quarantine_days = value;
+            set("quarantine_days");
+        }
+        if let Some(value) = layer.max_total_size {
+            self.config.max_total_size = Some(value);
+            set("max_total_size");
+        }
+        if let Some(value) = layer.min_free_percent {
+            self.config.min_free_percent = Some(value);
+            set("min_free_percent");
+        }
+        if let Some(value) = layer.max_artifacts_per_project {
+            self.config.max_artifacts_per_project = Some(value);
+            set("max_artifacts_per_project");
+        }
+        if let Some(value) = layer.policies {
+            self.config.policies = value;
+            set("policies");
+        }
+        if let Some(value) = layer.daemon_interval {
+            self.config.daemon_interval = value;
+            set("daemon_interval");
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key: &'static str, message: &str| ConfigError::Invalid {
+            key,
+            origin: self.origins.get(key).cloned().unwrap_or_else(|| "defaults".to_string()),
+            message: message.to_string(),
+        };
+        let url = &self.config.database_url;
+        if !["postgres://", "postgresql://", "sqlite:"].iter().any(|scheme| url.starts_with(scheme)) {
+            return Err(invalid("database_url", "expected a postgres:// or sqlite: URL"));
+        }
+        if self.config.scan_paths.is_empty() {
+            return Err(invalid("scan_paths", "at least one path is required"));
+        }
+        if self.config.scan_paths.iter().any(|path| path.trim().is_empty()) {
+            return Err(invalid("scan_paths", "paths must not be empty"));
+        }
+        if self.config.retention_days == 0 {
+            return Err(invalid("retention_days", "must be at least 1 day"));
+        }
+        if self.config.quarantine_days == 0 {
+            return Err(invalid("quarantine_days", "must be at least 1 day"));
+        }
+        if self.config.excluded_paths.iter().any(|path| path.trim().is_empty()) {
+            return Err(invalid("excluded_paths", "paths must not be empty"));
+        }
+        if self.config.max_depth == 0 {
+            return Err(invalid("max_depth", "must be at least 1"));
+        }
+        if let Some((path, _)) = self.config.scan_depths.iter().find(|(_, depth)| **depth == 0) {
+            return Err(invalid("scan_depths", &format!("depth for '{}' must be at least 1", path)));
+        }
+        if let Some(size) = &self.config.max_total_size {
+            match parse_size(size) {
+                Ok(0) => return Err(invalid("max_total_size", "must be more than 0 bytes")),
+                Ok(_) => {}
+                Err(message) => return Err(invalid("max_total_size", &message)),
+            }
+        }
+        if let Some(percent) = self.config.min_free_percent
+            && !(1..=99).contains(&percent)
+        {
+            return Err(invalid("min_free_percent", "must be between 1 and 99"));
+        }
+        if self.config.max_artifacts_per_project == Some(0) {
+            return Err(invalid("max_artifacts_per_project", "must be at least 1"));
+        }
+        match parse_interval(&self.config.daemon_interval) {
+            Ok(interval) if interval.as_secs() < 60 => return Err(invalid("daemon_interval", "must be at least 1 minute")),
+            Ok(_) => {}
+            Err(message) => return Err(invalid("daemon_interval", &message)),
+        }
+        for (i, block) in self.config.policies.iter().enumerate() {
+            block.validate().map_err(|message| invalid("policies", &message))?;
+            if self.config.policies[..i].iter().any(|earlier| earlier.name == block.name) {
+                return Err(invalid("policies", &format!("policy '{}' is defined twice", block.name)));
+            }
+        }
+        for rule in &self.config.rules {
+            rule.validate().map_err(|message| invalid("rules", &message))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn save_config(changes: &PartialConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path().ok_or("no config directory: set XDG_CONFIG_HOME or HOME, or pass --config")?;
+    save_config_to(changes, &path)
+}
+
+/// Writes the settings edited in the TUI that are set in `changes`, keeping
+/// every other key already in the file, so values that only came from the
+/// environment or flags are not made permanent. The database URL is never written.
+pub fn save_config_to(changes: &PartialConfig, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut layer = read_config_file(path)?.unwrap_or_default();
+    if let Some(scan_paths) = &changes.scan_paths {
+        layer.scan_paths = Some(scan_paths.clone());
+    }
+    if let Some(retention_days) = changes.retention_days {
+        layer.retention_days = Some(retention_days);
+    }
+    if let Some(excluded_paths) = &changes.excluded_paths {
+        layer.excluded_paths = Some(excluded_paths.clone());
+    }
+    let toml_string = toml::to_string(&layer)?;
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, toml_string)?;
+    Ok(())
+}
+
+/// Copies a config written by an older release into `target` the first time
+/// ratifact runs with XDG paths. Returns true when a file was migrated.
+pub fn migrate_legacy_config(legacy: &Path, target: &Path) -> std::io::Result<bool> {
+    if target.exists() || !legacy.is_file() {
+        return Ok(false);
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(legacy, target)?;
+    Ok(true)
+}
@@ -1,6 +1,19 @@
 // Configuration types
 
+use crate::cleanup::policy::PolicyBlock;
+use crate::rules::Rule;
+use crate::utils::{parse_interval, parse_size};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How deep below a scan path directories are searched unless configured.
+pub const DEFAULT_MAX_DEPTH: usize = 6;
+
+/// How long quarantined artifacts are kept before they are purged unless configured.
+pub const DEFAULT_QUARANTINE_DAYS: u32 = 7;
+
+/// How often `ratifact daemon` rescans and cleans up unless configured.
+pub const DEFAULT_DAEMON_INTERVAL: &str = "1d";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -11,6 +24,71 @@ pub struct Config {
     pub debug_logs_enabled: bool,
     #[serde(default)]
     pub excluded_paths: Vec<String>,
+    /// Extra artifact rules, checked before the built-in ones.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Directory levels searched below each scan path.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// Per scan path overrides of `max_depth`.
+    #[serde(default)]
+    pub scan_depths: BTreeMap<String, usize>,
+    /// Descend into symlinked directories while looking for projects.
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Never cross into another filesystem, such as a network share mounted below a scan path.
+    #[serde(default)]
+    pub one_file_system: bool,
+    /// Move artifacts into a holding area instead of deleting them.
+    #[serde(default)]
+    pub quarantine: bool,
+    /// Days a quarantined artifact can still be restored before it is purged.
+    #[serde(default = "default_quarantine_days")]
+    pub quarantine_days: u32,
+    /// Size such as "50GB" the artifacts may take together before the least recently used go.
+    #[serde(default)]
+    pub max_total_size: Option<String>,
+    /// Free space, in percent of a filesystem, that cleanup restores by deleting the least recently used artifacts on it.
+    #[serde(default)]
+    pub min_free_percent: Option<u8>,
+    /// Artifacts kept per project, the most recently used ones.
+    #[serde(default)]
+    pub max_artifacts_per_project: Option<usize>,
+    /// Retention for matching artifacts in place of `retention_days`; the first match applies.
+    #[serde(default)]
+    pub policies: Vec<PolicyBlock>,
+    /// Time between two passes of `ratifact daemon`, such as "6h".
+    #[serde(default = "default_daemon_interval")]
+    pub daemon_interval: String,
+}
+
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+fn default_quarantine_days() -> u32 {
+    DEFAULT_QUARANTINE_DAYS
+}
+
+fn default_daemon_interval() -> String {
+    DEFAULT_DAEMON_INTERVAL.to_string()
+}
+
+impl Config {
+    /// The search depth for `scan_path`, honoring `scan_depths`.
+    pub fn depth_for(&self, scan_path: &str) -> usize {
+        self.scan_depths.get(scan_path).copied().unwrap_or(self.max_depth)
+    }
+
+    /// `max_total_size` in bytes. Validation guarantees it parses.
+    pub fn max_total_bytes(&self) -> Option<u64> {
+        self.max_total_size.as_deref().and_then(|size| parse_size(size).ok())
+    }
+
+    /// `daemon_interval` as a duration. Validation guarantees it parses.
+    pub fn daemon_period(&self) -> std::time::Duration {
+        parse_interval(&self.daemon_interval).unwrap_or(std::time::Duration::from_secs(24 * 60 * 60))
+    }
 }
 
 impl Default for Config {
@@ -21,6 +99,59 @@ impl Default for Config {
             retention_days: 30,
             debug_logs_enabled: false,
             excluded_paths: vec![],
+            rules: vec![],
+            max_depth: DEFAULT_MAX_DEPTH,
+            scan_depths: BTreeMap::new(),
+            follow_symlinks: false,
+            one_file_system: false,
+            quarantine: false,
+            quarantine_days: DEFAULT_QUARANTINE_DAYS,
+            max_total_size: None,
+            min_free_percent: None,
+            max_artifacts_per_project: None,
+            policies: vec![],
+            daemon_interval: default_daemon_interval(),
         }
     }
 }
+
+/// One configuration layer (a file, the environment or CLI flags).
+/// Unset fields fall through to the layer below.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_paths: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_logs_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excluded_paths: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<Rule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_depths: Option<BTreeMap<String, usize>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_symlinks: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub one_file_system: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_free_percent: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_artifacts_per_project: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policies: Option<Vec<PolicyBlock>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daemon_interval: Option<String>,
+}
@@ -1,9 +1,24 @@
 // Database connection management
 
+use crate::db::postgres::PostgresStore;
+use crate::db::sqlite::SqliteStore;
+use crate::db::store::ArtifactStore;
 use sqlx::PgPool;
+use std::sync::Arc;
 
 pub async fn establish_connection(database_url: &str) -> Result<PgPool, sqlx::Error> {
     PgPool::connect(database_url).await
 }
 
-
+/// Opens the storage backend matching the scheme of `database_url`.
+pub async fn open_store(database_url: &str) -> Result<Arc<dyn ArtifactStore>, sqlx::Error> {
+    if database_url.starts_with("sqlite:") {
+        Ok(Arc::new(SqliteStore::connect(database_url).await?))
+    } else if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresStore::connect(database_url).await?))
+    } else {
+        Err(sqlx::Error::Configuration(
+            format!("Unsupported DATABASE_URL scheme: {}", database_url.split(':').next().unwrap_or("")).into(),
+        ))
+    }
+}
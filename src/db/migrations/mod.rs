@@ -0,0 +1,263 @@
+// Versioned schema migrations
+// Each backend has its own ordered list of SQL files; applied versions are tracked in schema_version
+
+use sqlx::sqlite::SqlitePool;
+use sqlx::{Connection, PgConnection, PgPool};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_builds",
+        sql: include_str!("postgres/0001_create_builds.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "normalize_artifacts",
+        sql: include_str!("postgres/0002_normalize_artifacts.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "artifact_verified",
+        sql: include_str!("postgres/0003_artifact_verified.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "artifact_description",
+        sql: include_str!("postgres/0004_artifact_description.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "dir_size_cache",
+        sql: include_str!("postgres/0005_dir_size_cache.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "disk_usage",
+        sql: include_str!("postgres/0006_disk_usage.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "quarantine",
+        sql: include_str!("postgres/0007_quarantine.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "deletions",
+        sql: include_str!("postgres/0008_deletions.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "last_use",
+        sql: include_str!("postgres/0009_last_use.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "pins",
+        sql: include_str!("postgres/0010_pins.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "daemon",
+        sql: include_str!("postgres/0011_daemon.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "build_activity",
+        sql: include_str!("postgres/0012_build_activity.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "dir_size_files",
+        sql: include_str!("postgres/0013_dir_size_files.sql"),
+    },
+];
+
+pub const SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_builds",
+        sql: include_str!("sqlite/0001_create_builds.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "normalize_artifacts",
+        sql: include_str!("sqlite/0002_normalize_artifacts.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "artifact_verified",
+        sql: include_str!("sqlite/0003_artifact_verified.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "artifact_description",
+        sql: include_str!("sqlite/0004_artifact_description.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "dir_size_cache",
+        sql: include_str!("sqlite/0005_dir_size_cache.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "disk_usage",
+        sql: include_str!("sqlite/0006_disk_usage.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "quarantine",
+        sql: include_str!("sqlite/0007_quarantine.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "deletions",
+        sql: include_str!("sqlite/0008_deletions.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "last_use",
+        sql: include_str!("sqlite/0009_last_use.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "pins",
+        sql: include_str!("sqlite/0010_pins.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "daemon",
+        sql: include_str!("sqlite/0011_daemon.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "build_activity",
+        sql: include_str!("sqlite/0012_build_activity.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "dir_size_files",
+        sql: include_str!("sqlite/0013_dir_size_files.sql"),
+    },
+];
+
+pub fn latest_version(migrations: &[Migration]) -> i64 {
+    migrations.iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+/// Refuses to run against a database written by a newer ratifact.
+fn check_not_newer(current: i64, migrations: &[Migration]) -> Result<(), sqlx::Error> {
+    let latest = latest_version(migrations);
+    if current > latest {
+        return Err(sqlx::Error::Configuration(
+            format!(
+                "Database schema version {} is newer than this ratifact supports ({}). Please upgrade ratifact.",
+                current, latest
+            )
+            .into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Key of the advisory lock that keeps two processes, such as the daemon and
+/// the TUI starting together, from migrating the same database at once.
+const POSTGRES_MIGRATION_LOCK: i64 = 0x7261_7469_6661_6374;
+
+pub async fn apply_postgres(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(POSTGRES_MIGRATION_LOCK)
+        .execute(&mut *conn)
+        .await?;
+    let result = apply_postgres_locked(&mut conn).await;
+    let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(POSTGRES_MIGRATION_LOCK)
+        .execute(&mut *conn)
+        .await;
+    if unlocked.is_err() {
+        // Closing the session is the only other way to let go of the lock
+        conn.close_on_drop();
+    }
+    let version = result?;
+    unlocked?;
+    Ok(version)
+}
+
+async fn apply_postgres_locked(conn: &mut PgConnection) -> Result<i64, sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )",
+    )
+    .execute(&mut *conn)
+    .await?;
+    let (current,): (Option<i64>,) = sqlx::query_as("SELECT MAX(version) FROM schema_version")
+        .fetch_one(&mut *conn)
+        .await?;
+    let current = current.unwrap_or(0);
+    check_not_newer(current, POSTGRES_MIGRATIONS)?;
+
+    for migration in POSTGRES_MIGRATIONS.iter().filter(|m| m.version > current) {
+        let mut tx = conn.begin().await?;
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_version (version, name) VALUES ($1, $2)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(latest_version(POSTGRES_MIGRATIONS).max(current))
+}
+
+pub async fn apply_sqlite(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await?;
+    let (current,): (Option<i64>,) = sqlx::query_as("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+    let mut current = current.unwrap_or(0);
+    check_not_newer(current, SQLITE_MIGRATIONS)?;
+
+    for migration in SQLITE_MIGRATIONS {
+        if migration.version <= current {
+            continue;
+        }
+        // BEGIN IMMEDIATE takes the write lock up front, so another process
+        // migrating at the same time waits and then finds the migration applied
+        let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+        let (applied,): (Option<i64>,) = sqlx::query_as("SELECT MAX(version) FROM schema_version")
+            .fetch_one(&mut *tx)
+            .await?;
+        current = applied.unwrap_or(0);
+        if current >= migration.version {
+            tx.rollback().await?;
+            continue;
+        }
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(chrono::Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        current = migration.version;
+    }
+    Ok(latest_version(SQLITE_MIGRATIONS).max(current))
+}
@@ -0,0 +1,9 @@
+-- Initial schema. IF NOT EXISTS so installs from before versioned migrations adopt it in place.
+CREATE TABLE IF NOT EXISTS builds (
+    id SERIAL PRIMARY KEY,
+    project_path TEXT NOT NULL,
+    language TEXT NOT NULL,
+    build_time TIMESTAMPTZ DEFAULT NOW(),
+    artifact_path TEXT NOT NULL,
+    size_bytes BIGINT
+);
@@ -0,0 +1,2 @@
+-- Comment line from a CACHEDIR.TAG in the artifact, shown as its description.
+ALTER TABLE artifacts ADD COLUMN description TEXT;
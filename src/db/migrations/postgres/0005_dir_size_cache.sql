@@ -0,0 +1,9 @@
+-- Size of the files directly inside each directory of an artifact, reused while
+-- the directory's inode and mtime are unchanged.
+CREATE TABLE dir_size_cache (
+    path TEXT PRIMARY KEY,
+    inode BIGINT NOT NULL,
+    mtime_ns BIGINT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    subdirs TEXT NOT NULL
+);
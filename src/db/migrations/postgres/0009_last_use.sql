@@ -0,0 +1,12 @@
+-- Signals of when an artifact was last used, so retention follows real use
+-- instead of the time ratifact first saw it.
+ALTER TABLE artifacts ADD COLUMN content_modified TIMESTAMPTZ;
+ALTER TABLE projects ADD COLUMN source_modified TIMESTAMPTZ;
+ALTER TABLE projects ADD COLUMN last_commit TIMESTAMPTZ;
+
+-- Cached entries predate file times; the next scan rebuilds them.
+DELETE FROM dir_size_cache;
+ALTER TABLE dir_size_cache ADD COLUMN newest_mtime_ns BIGINT NOT NULL DEFAULT 0;
+
+-- Why a policy picked an artifact, e.g. the signal that made it count as unused.
+ALTER TABLE deletions ADD COLUMN reason TEXT;
@@ -0,0 +1,25 @@
+-- Leases that keep the TUI, `ratifact clean` and the daemon from purging at
+-- the same time. A lease past expires_at is free to take over.
+CREATE TABLE locks (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    acquired_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+-- One row per scheduled daemon pass.
+CREATE TABLE daemon_runs (
+    id BIGSERIAL PRIMARY KEY,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL,
+    status TEXT NOT NULL,
+    artifacts BIGINT NOT NULL,
+    removed BIGINT NOT NULL,
+    quarantined BIGINT NOT NULL,
+    purged BIGINT NOT NULL,
+    failed BIGINT NOT NULL,
+    refused BIGINT NOT NULL,
+    freed_bytes BIGINT NOT NULL,
+    detail TEXT
+);
+CREATE INDEX daemon_runs_time_idx ON daemon_runs (started_at);
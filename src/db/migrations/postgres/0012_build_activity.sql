@@ -0,0 +1,12 @@
+-- Bursts of file changes the watcher saw inside an artifact, such as a build
+-- writing to target/, with the artifact's size before and after.
+CREATE TABLE build_activity (
+    id BIGSERIAL PRIMARY KEY,
+    artifact_id BIGINT NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ NOT NULL,
+    events BIGINT NOT NULL,
+    size_before BIGINT,
+    size_after BIGINT NOT NULL
+);
+CREATE INDEX build_activity_time_idx ON build_activity (ended_at);
@@ -0,0 +1,4 @@
+-- Names of the files directly inside each cached directory, so rescans can stat
+-- them and notice files rewritten in place. Older entries are rebuilt.
+DELETE FROM dir_size_cache;
+ALTER TABLE dir_size_cache ADD COLUMN files TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,80 @@
+-- Split the flat builds log into projects, artifacts, size observations and build events.
+CREATE TABLE projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL UNIQUE,
+    language TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL
+);
+
+CREATE TABLE artifacts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    path TEXT NOT NULL UNIQUE,
+    size_bytes BIGINT NOT NULL DEFAULT 0,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL
+);
+
+CREATE TABLE size_observations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    artifact_id INTEGER NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+    observed_at TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL
+);
+CREATE INDEX size_observations_artifact_idx ON size_observations (artifact_id, observed_at);
+
+CREATE TABLE build_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    artifact_id INTEGER NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+    event_time TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    size_before BIGINT,
+    size_after BIGINT NOT NULL
+);
+CREATE INDEX build_events_time_idx ON build_events (event_time);
+
+-- One-time backfill from the old per-scan rows
+INSERT INTO projects (path, language, first_seen, last_seen)
+SELECT b.project_path,
+       (SELECT b2.language FROM builds b2 WHERE b2.project_path = b.project_path
+        ORDER BY b2.build_time DESC, b2.id DESC LIMIT 1),
+       MIN(b.build_time),
+       MAX(b.build_time)
+FROM builds b
+GROUP BY b.project_path;
+
+INSERT INTO artifacts (project_id, path, size_bytes, first_seen, last_seen)
+SELECT (SELECT p.id FROM builds b2 JOIN projects p ON p.path = b2.project_path
+        WHERE b2.artifact_path = b.artifact_path
+        ORDER BY b2.build_time DESC, b2.id DESC LIMIT 1),
+       b.artifact_path,
+       COALESCE((SELECT b3.size_bytes FROM builds b3 WHERE b3.artifact_path = b.artifact_path
+                 ORDER BY b3.build_time DESC, b3.id DESC LIMIT 1), 0),
+       MIN(b.build_time),
+       MAX(b.build_time)
+FROM builds b
+GROUP BY b.artifact_path;
+
+-- Keep only rows where the size actually changed; repeated scans of the same size are dropped
+CREATE TEMPORARY TABLE legacy_changes AS
+SELECT c.artifact_path, c.observed_at, c.size_bytes, c.prev_size
+FROM (
+    SELECT artifact_path,
+           build_time AS observed_at,
+           COALESCE(size_bytes, 0) AS size_bytes,
+           LAG(COALESCE(size_bytes, 0)) OVER (PARTITION BY artifact_path ORDER BY build_time, id) AS prev_size
+    FROM builds
+) c
+WHERE c.prev_size IS NULL OR c.prev_size <> c.size_bytes;
+
+INSERT INTO size_observations (artifact_id, observed_at, size_bytes)
+SELECT a.id, l.observed_at, l.size_bytes
+FROM legacy_changes l JOIN artifacts a ON a.path = l.artifact_path;
+
+INSERT INTO build_events (artifact_id, event_time, kind, size_before, size_after)
+SELECT a.id, l.observed_at, CASE WHEN l.prev_size IS NULL THEN 'discovered' ELSE 'size_changed' END, l.prev_size, l.size_bytes
+FROM legacy_changes l JOIN artifacts a ON a.path = l.artifact_path;
+
+DROP TABLE legacy_changes;
+DROP TABLE builds;
@@ -0,0 +1,3 @@
+-- Whether a rule fingerprint confirmed the artifact was written by its build tool.
+-- Existing rows start unverified until the next scan checks them.
+ALTER TABLE artifacts ADD COLUMN verified BOOLEAN NOT NULL DEFAULT 0;
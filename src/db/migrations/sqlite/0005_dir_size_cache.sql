@@ -0,0 +1,9 @@
+-- Size of the files directly inside each directory of an artifact, reused while
+-- the directory's inode and mtime are unchanged.
+CREATE TABLE dir_size_cache (
+    path TEXT PRIMARY KEY,
+    inode INTEGER NOT NULL,
+    mtime_ns INTEGER NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    subdirs TEXT NOT NULL
+);
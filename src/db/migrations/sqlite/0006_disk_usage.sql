@@ -0,0 +1,7 @@
+-- Allocated size on disk, with hardlinked files counted once per scan.
+ALTER TABLE artifacts ADD COLUMN disk_bytes INTEGER;
+
+-- Cached entries predate on-disk sizes; the next scan rebuilds them.
+DELETE FROM dir_size_cache;
+ALTER TABLE dir_size_cache ADD COLUMN disk_bytes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE dir_size_cache ADD COLUMN linked TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,15 @@
+-- Artifacts moved into a holding area instead of being deleted, with what is
+-- needed to record them again when they are restored.
+CREATE TABLE quarantine (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    original_path TEXT NOT NULL,
+    holding_path TEXT NOT NULL UNIQUE,
+    project_path TEXT NOT NULL,
+    language TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    disk_bytes INTEGER,
+    verified BOOLEAN NOT NULL,
+    description TEXT,
+    quarantined_at TEXT NOT NULL
+);
+CREATE INDEX quarantine_time_idx ON quarantine (quarantined_at);
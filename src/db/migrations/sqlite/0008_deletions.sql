@@ -0,0 +1,15 @@
+-- Audit log of every deletion attempt. Rows outlive the artifacts they
+-- describe, so nothing here references the artifacts table.
+CREATE TABLE deletions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    disk_bytes INTEGER,
+    trigger TEXT NOT NULL,
+    username TEXT NOT NULL,
+    deleted_at TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    detail TEXT
+);
+CREATE INDEX deletions_time_idx ON deletions (deleted_at);
+CREATE INDEX deletions_path_idx ON deletions (path);
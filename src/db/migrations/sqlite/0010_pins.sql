@@ -0,0 +1,8 @@
+-- Artifacts kept out of bulk deletion and retention policies. Keyed by path,
+-- so a pin outlives the artifact being forgotten and found again.
+CREATE TABLE pins (
+    path TEXT PRIMARY KEY,
+    note TEXT,
+    pinned_at TEXT NOT NULL,
+    expires_at TEXT
+);
@@ -0,0 +1,25 @@
+-- Leases that keep the TUI, `ratifact clean` and the daemon from purging at
+-- the same time. A lease past expires_at is free to take over.
+CREATE TABLE locks (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    acquired_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+-- One row per scheduled daemon pass.
+CREATE TABLE daemon_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    status TEXT NOT NULL,
+    artifacts INTEGER NOT NULL,
+    removed INTEGER NOT NULL,
+    quarantined INTEGER NOT NULL,
+    purged INTEGER NOT NULL,
+    failed INTEGER NOT NULL,
+    refused INTEGER NOT NULL,
+    freed_bytes INTEGER NOT NULL,
+    detail TEXT
+);
+CREATE INDEX daemon_runs_time_idx ON daemon_runs (started_at);
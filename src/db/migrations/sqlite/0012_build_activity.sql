@@ -0,0 +1,12 @@
+-- Bursts of file changes the watcher saw inside an artifact, such as a build
+-- writing to target/, with the artifact's size before and after.
+CREATE TABLE build_activity (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    artifact_id INTEGER NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL,
+    events INTEGER NOT NULL,
+    size_before INTEGER,
+    size_after INTEGER NOT NULL
+);
+CREATE INDEX build_activity_time_idx ON build_activity (ended_at);
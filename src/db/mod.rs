@@ -1,5 +1,9 @@
 // Database module for ratifact by neura ai 
-// Handles SQLite connections and schema management
+// Handles PostgreSQL and SQLite connections and schema management
 
 pub mod connection;
+pub mod migrations;
+pub mod postgres;
 pub mod schema;
+pub mod sqlite;
+pub mod store;
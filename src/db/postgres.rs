@@ -0,0 +1,492 @@
+// PostgreSQL implementation of ArtifactStore
+
+use crate::db::connection::establish_connection;
+use crate::db::schema::create_tables;
+use crate::db::store::{
+    ARTIFACT_COLUMNS, ArtifactRecord, ArtifactRow, ArtifactStore, BuildActivity, BuildActivityRow, BuildEvent, BuildEventRow, DAEMON_RUN_COLUMNS, DELETION_COLUMNS,
+    DaemonRun, DaemonRunRow, DeletionRecord, DeletionRow, DirSizeEntry, DirSizeRow, DiskUsage,
+    EVENT_DISCOVERED, EVENT_SIZE_CHANGED, Lock, LockRow, ObservationOutcome, Pin, PinRow, QUARANTINE_COLUMNS, QuarantineEntry,
+    QuarantineRow, SizeObservation, UsageSignals,
+};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+
+pub struct PostgresStore {
+    pub pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(database_url: &str) -> Result<Self, sqlx::Error> {
+        let pool = establish_connection(database_url).await?;
+        create_tables(&pool).await?;
+        Ok(PostgresStore { pool })
+    }
+
+    async fn delete_orphan_projects(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM projects WHERE id NOT IN (SELECT project_id FROM artifacts)")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for PostgresStore {
+    fn backend_name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn record_observation(
+        &self,
+        project_path: &str,
+        language: &str,
+        artifact_path: &str,
+        size: DiskUsage,
+        verified: bool,
+        description: Option<&str>,
+    ) -> Result<ObservationOutcome, sqlx::Error> {
+        let now = Utc::now();
+        let disk_bytes = size.disk_bytes as i64;
+        let size = size.apparent_bytes;
+        let mut tx = self.pool.begin().await?;
+        let (project_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO projects (path, language, first_seen, last_seen) VALUES ($1, $2, $3, $3)
+             ON CONFLICT (path) DO UPDATE SET language = EXCLUDED.language, last_seen = EXCLUDED.last_seen
+             RETURNING id",
+        )
+        .bind(project_path)
+        .bind(language)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let existing: Option<(i64, i64)> = sqlx::query_as("SELECT id, size_bytes FROM artifacts WHERE path = $1")
+            .bind(artifact_path)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let (artifact_id, outcome) = match existing {
+            None => {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO artifacts (project_id, path, size_bytes, first_seen, last_seen, verified, description, disk_bytes)
+                     VALUES ($1, $2, $3, $4, $4, $5, $6, $7) RETURNING id",
+                )
+                .bind(project_id)
+                .bind(artifact_path)
+                .bind(size as i64)
+                .bind(now)
+                .bind(verified)
+                .bind(description)
+                .bind(disk_bytes)
+                .fetch_one(&mut *tx)
+                .await?;
+                (id, ObservationOutcome::New)
+            }
+            Some((id, previous)) => {
+                sqlx::query(
+                    "UPDATE artifacts SET project_id = $1, size_bytes = $2, last_seen = $3, verified = $4, description = $5,
+                     disk_bytes = $6 WHERE id = $7",
+                )
+                .bind(project_id)
+                .bind(size as i64)
+                .bind(now)
+                .bind(verified)
+                .bind(description)
+                .bind(disk_bytes)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                if previous as u64 == size {
+                    (id, ObservationOutcome::Unchanged)
+                } else {
+                    (id, ObservationOutcome::Changed { previous: previous as u64 })
+                }
+            }
+        };
+
+        let (kind, size_before) = match outcome {
+            ObservationOutcome::New => (EVENT_DISCOVERED, None),
+            ObservationOutcome::Changed { previous } => (EVENT_SIZE_CHANGED, Some(previous as i64)),
+            ObservationOutcome::Unchanged => {
+                tx.commit().await?;
+                return Ok(outcome);
+            }
+        };
+        sqlx::query("INSERT INTO size_observations (artifact_id, observed_at, size_bytes) VALUES ($1, $2, $3)")
+            .bind(artifact_id)
+            .bind(now)
+            .bind(size as i64)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO build_events (artifact_id, event_time, kind, size_before, size_after) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(artifact_id)
+        .bind(now)
+        .bind(kind)
+        .bind(size_before)
+        .bind(size as i64)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    async fn current_artifacts(&self) -> Result<Vec<ArtifactRecord>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, ArtifactRow>(&format!(
+            "SELECT {} FROM artifacts a JOIN projects p ON p.id = a.project_id LEFT JOIN pins pin ON pin.path = a.path
+             ORDER BY a.last_seen DESC, a.path",
+            ARTIFACT_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ArtifactRecord::from).collect())
+    }
+
+    async fn size_history(&self, artifact_path: &str) -> Result<Vec<SizeObservation>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (DateTime<Utc>, i64)>(
+            "SELECT o.observed_at, o.size_bytes FROM size_observations o JOIN artifacts a ON a.id = o.artifact_id
+             WHERE a.path = $1 ORDER BY o.observed_at, o.id",
+        )
+        .bind(artifact_path)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(observed_at, size)| SizeObservation { observed_at, size_bytes: size as u64 })
+            .collect())
+    }
+
+    async fn build_events(&self, limit: i64) -> Result<Vec<BuildEvent>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, BuildEventRow>(
+            "SELECT a.path, p.language, e.kind, e.size_before, e.size_after, e.event_time
+             FROM build_events e JOIN artifacts a ON a.id = e.artifact_id JOIN projects p ON p.id = a.project_id
+             ORDER BY e.event_time DESC, e.id DESC LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(BuildEvent::from).collect())
+    }
+
+    async fn total_builds(&self) -> Result<u64, sqlx::Error> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM build_events")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    async fn size_chart(&self) -> Result<Vec<(String, u64)>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, i64)>("SELECT path, size_bytes FROM artifacts ORDER BY size_bytes DESC")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|(path, size)| (path, size as u64)).collect())
+    }
+
+    async fn record_signals(&self, artifact_path: &str, signals: &UsageSignals) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(NEWER_CONTENT_MODIFIED)
+            .bind(signals.content_modified)
+            .bind(artifact_path)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE projects SET source_modified = $1, last_commit = $2
+             WHERE id = (SELECT project_id FROM artifacts WHERE path = $3)",
+        )
+        .bind(signals.source_modified)
+        .bind(signals.last_commit)
+        .bind(artifact_path)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_artifact(&self, artifact_path: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM artifacts WHERE path = $1")
+            .bind(artifact_path)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(DELETE_DIR_SIZES_UNDER).bind(artifact_path).execute(&self.pool).await?;
+        self.delete_orphan_projects().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn dir_size_cache(&self) -> Result<Vec<DirSizeEntry>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, DirSizeRow>("SELECT path, inode, mtime_ns, size_bytes, disk_bytes, linked, subdirs, newest_mtime_ns, files FROM dir_size_cache")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(DirSizeEntry::from).collect())
+    }
+
+    async fn replace_dir_sizes(&self, roots: &[String], entries: &[DirSizeEntry]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for root in roots {
+            sqlx::query(DELETE_DIR_SIZES_UNDER).bind(root).execute(&mut *tx).await?;
+        }
+        for entry in entries {
+            sqlx::query(
+                "INSERT INTO dir_size_cache (path, inode, mtime_ns, size_bytes, disk_bytes, linked, subdirs, newest_mtime_ns, files)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 ON CONFLICT (path) DO UPDATE SET inode = EXCLUDED.inode, mtime_ns = EXCLUDED.mtime_ns,
+                 size_bytes = EXCLUDED.size_bytes, disk_bytes = EXCLUDED.disk_bytes, linked = EXCLUDED.linked,
+                 subdirs = EXCLUDED.subdirs, newest_mtime_ns = EXCLUDED.newest_mtime_ns, files = EXCLUDED.files",
+            )
+            .bind(&entry.path)
+            .bind(entry.inode as i64)
+            .bind(entry.mtime_ns)
+            .bind(entry.size_bytes as i64)
+            .bind(entry.disk_bytes as i64)
+            .bind(serde_json::to_string(&entry.linked).unwrap_or_default())
+            .bind(serde_json::to_string(&entry.subdirs).unwrap_or_default())
+            .bind(entry.newest_mtime_ns)
+            .bind(serde_json::to_string(&entry.files).unwrap_or_default())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn quarantine_artifact(&self, artifact_path: &str, holding_path: &str) -> Result<Option<QuarantineEntry>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query_as::<_, QuarantineRow>(&format!(
+            "INSERT INTO quarantine (original_path, holding_path, project_path, language, size_bytes, disk_bytes, verified,
+             description, quarantined_at)
+             SELECT a.path, $2, p.path, p.language, a.size_bytes, a.disk_bytes, a.verified, a.description, $3
+             FROM artifacts a JOIN projects p ON p.id = a.project_id WHERE a.path = $1
+             RETURNING {}",
+            QUARANTINE_COLUMNS
+        ))
+        .bind(artifact_path)
+        .bind(holding_path)
+        .bind(Utc::now())
+        .fetch_optional(&mut *tx)
+        .await?;
+        if row.is_some() {
+            sqlx::query("DELETE FROM artifacts WHERE path = $1").bind(artifact_path).execute(&mut *tx).await?;
+            sqlx::query(DELETE_DIR_SIZES_UNDER).bind(artifact_path).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        self.delete_orphan_projects().await?;
+        Ok(row.map(QuarantineEntry::from))
+    }
+
+    async fn quarantined(&self) -> Result<Vec<QuarantineEntry>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, QuarantineRow>(&format!(
+            "SELECT {} FROM quarantine ORDER BY quarantined_at DESC, id DESC",
+            QUARANTINE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(QuarantineEntry::from).collect())
+    }
+
+    async fn expired_quarantine(&self, grace_days: u32) -> Result<Vec<QuarantineEntry>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, QuarantineRow>(&format!(
+            "SELECT {} FROM quarantine WHERE quarantined_at < NOW() - INTERVAL '1 day' * $1 ORDER BY quarantined_at, id",
+            QUARANTINE_COLUMNS
+        ))
+        .bind(grace_days as i32)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(QuarantineEntry::from).collect())
+    }
+
+    async fn forget_quarantined(&self, id: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM quarantine WHERE id = $1").bind(id).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn record_deletion(&self, deletion: &DeletionRecord) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO deletions (path, size_bytes, disk_bytes, trigger, username, deleted_at, outcome, detail, reason)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(&deletion.path)
+        .bind(deletion.size_bytes as i64)
+        .bind(deletion.disk_bytes.map(|bytes| bytes as i64))
+        .bind(&deletion.trigger)
+        .bind(&deletion.user)
+        .bind(deletion.deleted_at)
+        .bind(&deletion.outcome)
+        .bind(&deletion.detail)
+        .bind(&deletion.reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn deletions(&self, path: Option<&str>, limit: i64) -> Result<Vec<DeletionRecord>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, DeletionRow>(&format!(
+            "SELECT {} FROM deletions
+             WHERE $1 IS NULL OR path = $1 OR substr(path, 1, length($1) + 1) = $1 || '/'
+             ORDER BY deleted_at DESC, id DESC LIMIT $2",
+            DELETION_COLUMNS
+        ))
+        .bind(path)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(DeletionRecord::from).collect())
+    }
+
+    async fn pin(&self, pin: &Pin) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO pins (path, note, pinned_at, expires_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (path) DO UPDATE SET note = excluded.note, pinned_at = excluded.pinned_at, expires_at = excluded.expires_at",
+        )
+        .bind(&pin.path)
+        .bind(&pin.note)
+        .bind(pin.pinned_at)
+        .bind(pin.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn unpin(&self, path: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM pins WHERE path = $1").bind(path).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn pins(&self) -> Result<Vec<Pin>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, PinRow>("SELECT path, note, pinned_at, expires_at FROM pins ORDER BY path")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(Pin::from).collect())
+    }
+
+    async fn artifact(&self, path: &str) -> Result<Option<ArtifactRecord>, sqlx::Error> {
+        let row = sqlx::query_as::<_, ArtifactRow>(&format!(
+            "SELECT {} FROM artifacts a JOIN projects p ON p.id = a.project_id LEFT JOIN pins pin ON pin.path = a.path
+             WHERE a.path = $1",
+            ARTIFACT_COLUMNS
+        ))
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ArtifactRecord::from))
+    }
+
+    async fn record_activity(&self, activity: &BuildActivity) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO build_activity (artifact_id, started_at, ended_at, events, size_before, size_after)
+             SELECT id, $2, $3, $4, $5, $6 FROM artifacts WHERE path = $1",
+        )
+        .bind(&activity.artifact_path)
+        .bind(activity.started_at)
+        .bind(activity.ended_at)
+        .bind(activity.events as i64)
+        .bind(activity.size_before.map(|size| size as i64))
+        .bind(activity.size_after as i64)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() > 0 {
+            sqlx::query(NEWER_CONTENT_MODIFIED)
+                .bind(activity.ended_at)
+                .bind(&activity.artifact_path)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn build_activity(&self, limit: i64) -> Result<Vec<BuildActivity>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, BuildActivityRow>(
+            "SELECT a.path, b.started_at, b.ended_at, b.events, b.size_before, b.size_after
+             FROM build_activity b JOIN artifacts a ON a.id = b.artifact_id
+             ORDER BY b.ended_at DESC, b.id DESC LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(BuildActivity::from).collect())
+    }
+
+    async fn acquire_lock(&self, lock: &Lock) -> Result<Option<Lock>, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO locks (name, holder, acquired_at, expires_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (name) DO UPDATE SET holder = excluded.holder, acquired_at = excluded.acquired_at, expires_at = excluded.expires_at
+             WHERE locks.expires_at <= excluded.acquired_at",
+        )
+        .bind(&lock.name)
+        .bind(&lock.holder)
+        .bind(lock.acquired_at)
+        .bind(lock.expires_at)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() > 0 {
+            return Ok(None);
+        }
+        let row = sqlx::query_as::<_, LockRow>("SELECT name, holder, acquired_at, expires_at FROM locks WHERE name = $1")
+            .bind(&lock.name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(Lock::from))
+    }
+
+    async fn renew_lock(&self, name: &str, holder: &str, expires_at: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("UPDATE locks SET expires_at = $3 WHERE name = $1 AND holder = $2")
+            .bind(name)
+            .bind(holder)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn release_lock(&self, name: &str, holder: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM locks WHERE name = $1 AND holder = $2")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn record_daemon_run(&self, run: &DaemonRun) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO daemon_runs (started_at, finished_at, status, artifacts, removed, quarantined, purged, failed, refused, freed_bytes, detail)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(run.started_at)
+        .bind(run.finished_at)
+        .bind(&run.status)
+        .bind(run.artifacts as i64)
+        .bind(run.removed as i64)
+        .bind(run.quarantined as i64)
+        .bind(run.purged as i64)
+        .bind(run.failed as i64)
+        .bind(run.refused as i64)
+        .bind(run.freed_bytes as i64)
+        .bind(&run.detail)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn daemon_runs(&self, limit: i64) -> Result<Vec<DaemonRun>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, DaemonRunRow>(&format!(
+            "SELECT {} FROM daemon_runs ORDER BY started_at DESC, id DESC LIMIT $1",
+            DAEMON_RUN_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(DaemonRun::from).collect())
+    }
+}
+
+/// Records when an artifact's contents last changed, keeping the newer time
+/// when a scan and the watcher both recorded one.
+const NEWER_CONTENT_MODIFIED: &str = "UPDATE artifacts
+     SET content_modified = CASE WHEN content_modified IS NULL OR content_modified < $1 THEN $1 ELSE content_modified END
+     WHERE path = $2";
+
+/// Removes the cache entries of a directory and everything below it.
+const DELETE_DIR_SIZES_UNDER: &str =
+    "DELETE FROM dir_size_cache WHERE path = $1 OR substr(path, 1, length($1) + 1) = $1 || '/'";
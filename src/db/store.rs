@@ -0,0 +1,537 @@
+// Storage backend abstraction
+// Every query the app runs goes through ArtifactStore so the backend can be swapped
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// A currently known artifact directory and the project it belongs to.
+#[derive(Debug, Clone)]
+pub struct ArtifactRecord {
+    pub path: String,
+    pub project_path: String,
+    pub language: String,
+    pub size_bytes: u64,
+    /// Allocated size, None until the artifact is rescanned by a release that measures it.
+    pub disk_bytes: Option<u64>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// A rule fingerprint or CACHEDIR.TAG confirmed the directory is build output.
+    pub verified: bool,
+    /// Comment line of the directory's CACHEDIR.TAG.
+    pub description: Option<String>,
+    pub signals: UsageSignals,
+    /// Set while the artifact is pinned, including after the pin expired.
+    pub pin: Option<Pin>,
+}
+
+impl ArtifactRecord {
+    /// Whether an unexpired pin keeps the artifact out of bulk deletion and retention.
+    pub fn is_pinned(&self, now: DateTime<Utc>) -> bool {
+        self.pin.as_ref().is_some_and(|pin| pin.is_active(now))
+    }
+}
+
+/// An artifact kept out of bulk deletion and retention policies.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Pin {
+    pub path: String,
+    pub note: Option<String>,
+    pub pinned_at: DateTime<Utc>,
+    /// The pin no longer applies from then on; None keeps it until unpinned.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Pin {
+    /// A pin of `path` from `now`, expiring after `days` if given.
+    pub fn new(path: String, note: Option<String>, days: Option<u32>, now: DateTime<Utc>) -> Self {
+        let expires_at = days.map(|days| now + chrono::Duration::days(days as i64));
+        Pin { path, note, pinned_at: now, expires_at }
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// When an artifact and its project were last touched, as of the latest scan.
+/// Each is None until a scan could determine it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageSignals {
+    /// Newest modification time of anything inside the artifact.
+    pub content_modified: Option<DateTime<Utc>>,
+    /// Newest modification time of the project's own files, outside its artifacts.
+    pub source_modified: Option<DateTime<Utc>>,
+    /// Time of the last commit touching the project, when it is in a git repository.
+    pub last_commit: Option<DateTime<Utc>>,
+}
+
+/// A build event: an artifact appearing for the first time or changing size.
+#[derive(Debug, Clone)]
+pub struct BuildEvent {
+    pub artifact_path: String,
+    pub language: String,
+    pub kind: String,
+    pub size_before: Option<u64>,
+    pub size_after: u64,
+    pub event_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeObservation {
+    pub observed_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// Size of an artifact: the sum of file lengths, and the blocks actually
+/// allocated with hardlinked files counted once per scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DiskUsage {
+    pub apparent_bytes: u64,
+    pub disk_bytes: u64,
+}
+
+impl DiskUsage {
+    pub fn add(&mut self, other: DiskUsage) {
+        self.apparent_bytes += other.apparent_bytes;
+        self.disk_bytes += other.disk_bytes;
+    }
+}
+
+/// Sizes where nothing is sparse or shared.
+impl From<u64> for DiskUsage {
+    fn from(bytes: u64) -> Self {
+        DiskUsage { apparent_bytes: bytes, disk_bytes: bytes }
+    }
+}
+
+/// A file with more than one hard link, identified by device and inode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LinkedFile {
+    pub device: u64,
+    pub inode: u64,
+    pub disk_bytes: u64,
+}
+
+/// Cached listing and size of one directory inside an artifact, reused while its
+/// inode and mtime match and its files are unchanged. Only files directly inside
+/// count; subdirectories have their own entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirSizeEntry {
+    pub path: String,
+    pub inode: u64,
+    pub mtime_ns: i64,
+    pub size_bytes: u64,
+    /// Allocated size of the files with a single link.
+    pub disk_bytes: u64,
+    /// Files with several links, counted once per scan whichever directory sees them first.
+    pub linked: Vec<LinkedFile>,
+    /// Names of the subdirectories, so an unchanged directory is never listed again.
+    pub subdirs: Vec<String>,
+    /// Names of the regular files, stat'ed again on every rescan.
+    pub files: Vec<String>,
+    /// Newest modification time of the directory and the files directly in it.
+    pub newest_mtime_ns: i64,
+}
+
+/// An artifact moved into a holding area instead of being deleted, with what
+/// is needed to record it again on restore.
+#[derive(Debug, Clone)]
+pub struct QuarantineEntry {
+    pub id: i64,
+    pub original_path: String,
+    pub holding_path: String,
+    pub project_path: String,
+    pub language: String,
+    pub size_bytes: u64,
+    pub disk_bytes: Option<u64>,
+    pub verified: bool,
+    pub description: Option<String>,
+    pub quarantined_at: DateTime<Utc>,
+}
+
+/// One entry of the deletion audit log: an artifact that was removed,
+/// quarantined or purged, or that a deletion left behind.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeletionRecord {
+    pub path: String,
+    /// Recorded size of the artifact when it was deleted.
+    pub size_bytes: u64,
+    pub disk_bytes: Option<u64>,
+    /// What asked for the deletion, e.g. "manual" or "retention".
+    pub trigger: String,
+    pub user: String,
+    pub deleted_at: DateTime<Utc>,
+    /// "removed", "quarantined", "partial", "refused" or "failed".
+    pub outcome: String,
+    /// Why a deletion did not complete.
+    pub detail: Option<String>,
+    /// Why a policy picked the artifact, e.g. how long it went unused.
+    pub reason: Option<String>,
+}
+
+/// A burst of changes inside an artifact, such as a build writing to it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuildActivity {
+    pub artifact_path: String,
+    /// The first change the watcher saw.
+    pub started_at: DateTime<Utc>,
+    /// The last change before the artifact went quiet.
+    pub ended_at: DateTime<Utc>,
+    /// File system events in the burst.
+    pub events: u64,
+    /// Recorded size before the burst, None if it was never measured.
+    pub size_before: Option<u64>,
+    pub size_after: u64,
+}
+
+impl BuildActivity {
+    /// Bytes the burst added, negative when it freed space.
+    pub fn bytes_changed(&self) -> i64 {
+        self.size_after as i64 - self.size_before.unwrap_or(0) as i64
+    }
+}
+
+/// A named lease, such as the cleanup lock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lock {
+    pub name: String,
+    /// Who holds it, e.g. "daemon (pid 4242)".
+    pub holder: String,
+    pub acquired_at: DateTime<Utc>,
+    /// After this the lease is free to take over, in case the holder died.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// The result of one daemon pass.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DaemonRun {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// "completed", "skipped" when another cleanup held the lock, or "failed".
+    pub status: String,
+    /// Artifacts the rescan found.
+    pub artifacts: u64,
+    pub removed: u64,
+    pub quarantined: u64,
+    /// Purged from quarantine after the grace period.
+    pub purged: u64,
+    pub failed: u64,
+    pub refused: u64,
+    /// Apparent size of what was removed from disk.
+    pub freed_bytes: u64,
+    /// Why the pass was skipped or failed.
+    pub detail: Option<String>,
+}
+
+/// What recording a scanned artifact changed in the store.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObservationOutcome {
+    New,
+    Changed { previous: u64 },
+    Unchanged,
+}
+
+pub const EVENT_DISCOVERED: &str = "discovered";
+pub const EVENT_SIZE_CHANGED: &str = "size_changed";
+
+#[async_trait]
+pub trait ArtifactStore: Send + Sync {
+    /// Short backend name, e.g. "postgres" or "sqlite".
+    fn backend_name(&self) -> &'static str;
+
+    /// Upserts the project and artifact seen by a scan. A size observation and a
+    /// build event are only written when the artifact is new or its apparent size changed.
+    async fn record_observation(
+        &self,
+        project_path: &str,
+        language: &str,
+        artifact_path: &str,
+        size: DiskUsage,
+        verified: bool,
+        description: Option<&str>,
+    ) -> Result<ObservationOutcome, sqlx::Error>;
+
+    /// Current artifacts, most recently seen first.
+    async fn current_artifacts(&self) -> Result<Vec<ArtifactRecord>, sqlx::Error>;
+
+    async fn size_history(&self, artifact_path: &str) -> Result<Vec<SizeObservation>, sqlx::Error>;
+
+    async fn build_events(&self, limit: i64) -> Result<Vec<BuildEvent>, sqlx::Error>;
+
+    async fn total_builds(&self) -> Result<u64, sqlx::Error>;
+
+    /// Current size per artifact, biggest first.
+    async fn size_chart(&self) -> Result<Vec<(String, u64)>, sqlx::Error>;
+
+    /// Stores the usage signals a scan found for the artifact and its project.
+    /// A newer content time already recorded, such as by the watcher, is kept.
+    async fn record_signals(&self, artifact_path: &str, signals: &UsageSignals) -> Result<(), sqlx::Error>;
+
+    /// Deletes the artifact and its cached directory sizes.
+    async fn delete_artifact(&self, artifact_path: &str) -> Result<u64, sqlx::Error>;
+
+    async fn dir_size_cache(&self) -> Result<Vec<DirSizeEntry>, sqlx::Error>;
+
+    /// Replaces the cached entries at and below each of `roots` with `entries`.
+    async fn replace_dir_sizes(&self, roots: &[String], entries: &[DirSizeEntry]) -> Result<(), sqlx::Error>;
+
+    /// Records that the artifact now lives at `holding_path` and forgets it as an
+    /// artifact. None when `artifact_path` is not a known artifact.
+    async fn quarantine_artifact(&self, artifact_path: &str, holding_path: &str) -> Result<Option<QuarantineEntry>, sqlx::Error>;
+
+    /// Quarantined artifacts, most recently quarantined first.
+    async fn quarantined(&self) -> Result<Vec<QuarantineEntry>, sqlx::Error>;
+
+    /// Quarantined artifacts whose `grace_days` have run out.
+    async fn expired_quarantine(&self, grace_days: u32) -> Result<Vec<QuarantineEntry>, sqlx::Error>;
+
+    /// Forgets a quarantined artifact once it was restored or purged.
+    async fn forget_quarantined(&self, id: i64) -> Result<u64, sqlx::Error>;
+
+    /// Appends an entry to the deletion audit log.
+    async fn record_deletion(&self, deletion: &DeletionRecord) -> Result<(), sqlx::Error>;
+
+    /// The latest `limit` audit log entries, newest first, optionally only
+    /// those at or below `path`.
+    async fn deletions(&self, path: Option<&str>, limit: i64) -> Result<Vec<DeletionRecord>, sqlx::Error>;
+
+    /// Pins `pin.path`, replacing any earlier pin of it.
+    async fn pin(&self, pin: &Pin) -> Result<(), sqlx::Error>;
+
+    /// Removes the pin of `path`. Returns how many were removed.
+    async fn unpin(&self, path: &str) -> Result<u64, sqlx::Error>;
+
+    /// Every pin, expired ones included, by path.
+    async fn pins(&self) -> Result<Vec<Pin>, sqlx::Error>;
+
+    /// The current artifact at `path`, if it is recorded.
+    async fn artifact(&self, path: &str) -> Result<Option<ArtifactRecord>, sqlx::Error>;
+
+    /// Records a burst of changes to a known artifact, which also counts as
+    /// its last use. Does nothing when the artifact is not recorded.
+    async fn record_activity(&self, activity: &BuildActivity) -> Result<(), sqlx::Error>;
+
+    /// Build activity, newest first.
+    async fn build_activity(&self, limit: i64) -> Result<Vec<BuildActivity>, sqlx::Error>;
+
+    /// Takes `lock` unless an unexpired lock of the same name is held.
+    /// Returns the lock in the way when it is.
+    async fn acquire_lock(&self, lock: &Lock) -> Result<Option<Lock>, sqlx::Error>;
+
+    /// Extends the lock `name` to `expires_at` if `holder` still holds it.
+    async fn renew_lock(&self, name: &str, holder: &str, expires_at: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+
+    /// Releases the lock `name` if `holder` still holds it.
+    async fn release_lock(&self, name: &str, holder: &str) -> Result<u64, sqlx::Error>;
+
+    async fn record_daemon_run(&self, run: &DaemonRun) -> Result<(), sqlx::Error>;
+
+    /// Daemon passes, newest first.
+    async fn daemon_runs(&self, limit: i64) -> Result<Vec<DaemonRun>, sqlx::Error>;
+}
+
+/// Row shape shared by the backends for artifact queries.
+pub type ArtifactRow = (
+    String,
+    String,
+    String,
+    i64,
+    Option<i64>,
+    DateTime<Utc>,
+    DateTime<Utc>,
+    bool,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+);
+
+impl From<ArtifactRow> for ArtifactRecord {
+    fn from(
+        (
+            path,
+            project_path,
+            language,
+            size_bytes,
+            disk_bytes,
+            first_seen,
+            last_seen,
+            verified,
+            description,
+            content_modified,
+            source_modified,
+            last_commit,
+            pin_note,
+            pinned_at,
+            pin_expires_at,
+        ): ArtifactRow,
+    ) -> Self {
+        let pin = pinned_at.map(|pinned_at| Pin { path: path.clone(), note: pin_note, pinned_at, expires_at: pin_expires_at });
+        ArtifactRecord {
+            path,
+            project_path,
+            language,
+            size_bytes: size_bytes as u64,
+            disk_bytes: disk_bytes.map(|bytes| bytes as u64),
+            first_seen,
+            last_seen,
+            verified,
+            description,
+            signals: UsageSignals { content_modified, source_modified, last_commit },
+            pin,
+        }
+    }
+}
+
+/// Columns selected into an ArtifactRow, from artifacts `a` joined with
+/// projects `p` and left joined with pins `pin`.
+pub const ARTIFACT_COLUMNS: &str = "a.path, p.path, p.language, a.size_bytes, a.disk_bytes, a.first_seen, a.last_seen, a.verified,
+     a.description, a.content_modified, p.source_modified, p.last_commit, pin.note, pin.pinned_at, pin.expires_at";
+
+/// Row shape shared by the backends for pins.
+pub type PinRow = (String, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>);
+
+impl From<PinRow> for Pin {
+    fn from((path, note, pinned_at, expires_at): PinRow) -> Self {
+        Pin { path, note, pinned_at, expires_at }
+    }
+}
+
+/// Row shape shared by the backends for the directory size cache.
+pub type DirSizeRow = (String, i64, i64, i64, i64, String, String, i64, String);
+
+impl From<DirSizeRow> for DirSizeEntry {
+    fn from((path, inode, mtime_ns, size_bytes, disk_bytes, linked, subdirs, newest_mtime_ns, files): DirSizeRow) -> Self {
+        DirSizeEntry {
+            path,
+            inode: inode as u64,
+            mtime_ns,
+            size_bytes: size_bytes as u64,
+            disk_bytes: disk_bytes as u64,
+            linked: serde_json::from_str(&linked).unwrap_or_default(),
+            subdirs: serde_json::from_str(&subdirs).unwrap_or_default(),
+            files: serde_json::from_str(&files).unwrap_or_default(),
+            newest_mtime_ns,
+        }
+    }
+}
+
+/// Row shape shared by the backends for quarantine queries.
+pub type QuarantineRow = (i64, String, String, String, String, i64, Option<i64>, bool, Option<String>, DateTime<Utc>);
+
+impl From<QuarantineRow> for QuarantineEntry {
+    fn from(
+        (id, original_path, holding_path, project_path, language, size_bytes, disk_bytes, verified, description, quarantined_at): QuarantineRow,
+    ) -> Self {
+        QuarantineEntry {
+            id,
+            original_path,
+            holding_path,
+            project_path,
+            language,
+            size_bytes: size_bytes as u64,
+            disk_bytes: disk_bytes.map(|bytes| bytes as u64),
+            verified,
+            description,
+            quarantined_at,
+        }
+    }
+}
+
+/// Columns selected into a QuarantineRow.
+pub const QUARANTINE_COLUMNS: &str =
+    "id, original_path, holding_path, project_path, language, size_bytes, disk_bytes, verified, description, quarantined_at";
+
+/// Row shape shared by the backends for deletion audit queries.
+pub type DeletionRow = (String, i64, Option<i64>, String, String, DateTime<Utc>, String, Option<String>, Option<String>);
+
+impl From<DeletionRow> for DeletionRecord {
+    fn from((path, size_bytes, disk_bytes, trigger, user, deleted_at, outcome, detail, reason): DeletionRow) -> Self {
+        DeletionRecord {
+            path,
+            size_bytes: size_bytes as u64,
+            disk_bytes: disk_bytes.map(|bytes| bytes as u64),
+            trigger,
+            user,
+            deleted_at,
+            outcome,
+            detail,
+            reason,
+        }
+    }
+}
+
+/// Columns selected into a DeletionRow.
+pub const DELETION_COLUMNS: &str = "path, size_bytes, disk_bytes, trigger, username, deleted_at, outcome, detail, reason";
+
+/// Row shape shared by the backends for build activity.
+pub type BuildActivityRow = (String, DateTime<Utc>, DateTime<Utc>, i64, Option<i64>, i64);
+
+impl From<BuildActivityRow> for BuildActivity {
+    fn from((artifact_path, started_at, ended_at, events, size_before, size_after): BuildActivityRow) -> Self {
+        BuildActivity {
+            artifact_path,
+            started_at,
+            ended_at,
+            events: events as u64,
+            size_before: size_before.map(|size| size as u64),
+            size_after: size_after as u64,
+        }
+    }
+}
+
+/// Row shape shared by the backends for locks.
+pub type LockRow = (String, String, DateTime<Utc>, DateTime<Utc>);
+
+impl From<LockRow> for Lock {
+    fn from((name, holder, acquired_at, expires_at): LockRow) -> Self {
+        Lock { name, holder, acquired_at, expires_at }
+    }
+}
+
+/// Row shape shared by the backends for daemon runs.
+pub type DaemonRunRow = (DateTime<Utc>, DateTime<Utc>, String, i64, i64, i64, i64, i64, i64, i64, Option<String>);
+
+impl From<DaemonRunRow> for DaemonRun {
+    fn from(
+        (started_at, finished_at, status, artifacts, removed, quarantined, purged, failed, refused, freed_bytes, detail): DaemonRunRow,
+    ) -> Self {
+        DaemonRun {
+            started_at,
+            finished_at,
+            status,
+            artifacts: artifacts as u64,
+            removed: removed as u64,
+            quarantined: quarantined as u64,
+            purged: purged as u64,
+            failed: failed as u64,
+            refused: refused as u64,
+            freed_bytes: freed_bytes as u64,
+            detail,
+        }
+    }
+}
+
+/// Columns selected into a DaemonRunRow.
+pub const DAEMON_RUN_COLUMNS: &str =
+    "started_at, finished_at, status, artifacts, removed, quarantined, purged, failed, refused, freed_bytes, detail";
+
+/// Row shape shared by the backends for build event queries.
+pub type BuildEventRow = (String, String, String, Option<i64>, i64, DateTime<Utc>);
+
+impl From<BuildEventRow> for BuildEvent {
+    fn from((artifact_path, language, kind, size_before, size_after, event_time): BuildEventRow) -> Self {
+        BuildEvent {
+            artifact_path,
+            language,
+            kind,
+            size_before: size_before.map(|s| s as u64),
+            size_after: size_after as u64,
+            event_time,
+        }
+    }
+}
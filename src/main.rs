@@ -1,5 +1,9 @@
+mod cleanup;
+mod cli;
 mod config;
 mod db;
+mod rules;
+mod scanner;
 mod tracking;
 mod ui;
 mod utils;
@@ -8,6 +12,8 @@ use ratatui::crossterm::{
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use clap::Parser;
+use cli::Cli;
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::io::stdout;
 use ui::app::App;
@@ -40,13 +46,39 @@ mod watcher_tests {
     include!("tests/watcher_tests.rs");
 }
 
+#[cfg(test)]
+mod scanner_tests {
+    include!("tests/scanner_tests.rs");
+}
+
 #[cfg(test)]
 mod utils_tests {
     include!("tests/utils_tests.rs");
 }
 
+#[cfg(test)]
+mod cli_tests {
+    include!("tests/cli_tests.rs");
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Headless subcommands never touch the terminal
+    let cli = Cli::parse();
+    if let Some(path) = cli.config.clone() {
+        config::settings::set_config_override(path);
+    }
+    let config = match config::settings::load_config(cli.config_overrides()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("ratifact: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(command) = cli.command {
+        return cli::commands::run(command, cli.format, config).await;
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = stdout();
@@ -55,10 +87,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // Initialize the app
-    let mut app = match App::new().await {
+    let mut app = match App::new(config).await {
         Ok(app) => app,
         Err(e) => {
             log_to_file(&format!("App init error: {:?}", e));
+            // Leave the terminal usable so the error is readable
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
             return Err(e);
         }
     };
@@ -0,0 +1,32 @@
+// Cache directory tags
+// See https://bford.info/cachedir/ for the format
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub const CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+
+/// Every valid tag starts with exactly these bytes.
+pub const CACHEDIR_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// A valid CACHEDIR.TAG found in a directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheDirTag {
+    /// The first comment line after the signature, without the leading `#`.
+    pub description: Option<String>,
+}
+
+/// Reads `dir/CACHEDIR.TAG`, returning None when it is missing or the
+/// signature does not match.
+pub fn read_cachedir_tag(dir: &Path) -> Option<CacheDirTag> {
+    let mut head = Vec::with_capacity(512);
+    File::open(dir.join(CACHEDIR_TAG)).ok()?.take(512).read_to_end(&mut head).ok()?;
+    let rest = head.strip_prefix(CACHEDIR_SIGNATURE)?;
+    let description = String::from_utf8_lossy(rest)
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(str::to_string);
+    Some(CacheDirTag { description })
+}
@@ -0,0 +1,293 @@
+// Artifact rules
+// A rule ties an ecosystem's marker files to the artifact directories that are safe to purge
+
+pub mod cachedir;
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// One ecosystem: a directory containing any of `markers` is a project, and
+/// `artifacts` (relative to that directory) are build outputs safe to delete.
+/// An artifact only counts as verified when one of its `fingerprints`, files
+/// the build tool leaves inside it, or a valid CACHEDIR.TAG is present.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub ecosystem: String,
+    /// File names or glob patterns such as `*.csproj`.
+    pub markers: Vec<String>,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// Paths relative to the project inside one of the artifacts, e.g.
+    /// `target/.rustc_info.json`. Glob patterns are allowed.
+    #[serde(default)]
+    pub fingerprints: Vec<String>,
+}
+
+impl Rule {
+    /// Checks a user-supplied rule, returning a message on the first problem.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ecosystem.trim().is_empty() {
+            return Err("ecosystem must not be empty".to_string());
+        }
+        if self.markers.is_empty() {
+            return Err(format!("rule '{}' needs at least one marker", self.ecosystem));
+        }
+        for marker in &self.markers {
+            if marker.is_empty() || marker.contains('/') || marker.contains('\\') {
+                return Err(format!("rule '{}': marker '{}' must be a file name", self.ecosystem, marker));
+            }
+            Pattern::new(marker).map_err(|e| format!("rule '{}': marker '{}': {}", self.ecosystem, marker, e))?;
+        }
+        for artifact in &self.artifacts {
+            if !is_inside_project(artifact) {
+                return Err(format!(
+                    "rule '{}': artifact '{}' must be a relative path inside the project",
+                    self.ecosystem, artifact
+                ));
+            }
+        }
+        for fingerprint in &self.fingerprints {
+            if !is_inside_project(fingerprint) || self.artifact_of(fingerprint).is_none() {
+                return Err(format!(
+                    "rule '{}': fingerprint '{}' must be a path inside one of the artifacts",
+                    self.ecosystem, fingerprint
+                ));
+            }
+            Pattern::new(fingerprint)
+                .map_err(|e| format!("rule '{}': fingerprint '{}': {}", self.ecosystem, fingerprint, e))?;
+        }
+        Ok(())
+    }
+
+    /// The artifact a fingerprint lives in.
+    fn artifact_of(&self, fingerprint: &str) -> Option<&str> {
+        let fingerprint = Path::new(fingerprint);
+        self.artifacts
+            .iter()
+            .map(String::as_str)
+            .find(|artifact| fingerprint.starts_with(artifact) && fingerprint != Path::new(artifact))
+    }
+
+    /// Whether `artifact` of the project at `dir` holds one of its fingerprints.
+    fn verifies(&self, dir: &Path, artifact: &str) -> bool {
+        self.fingerprints
+            .iter()
+            .filter(|fingerprint| self.artifact_of(fingerprint) == Some(artifact))
+            .any(|fingerprint| {
+                let pattern = format!("{}/{}", Pattern::escape(&dir.to_string_lossy()), fingerprint);
+                glob::glob(&pattern).map(|mut paths| paths.any(|p| p.is_ok())).unwrap_or(false)
+            })
+    }
+}
+
+fn is_inside_project(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Rules that ship with ratifact, in the order languages are detected:
+/// (ecosystem, markers, artifacts, fingerprints).
+type BuiltinRule = (&'static str, &'static [&'static str], &'static [&'static str], &'static [&'static str]);
+
+const BUILTIN_RULES: &[BuiltinRule] = &[
+    (
+        "Rust",
+        &["Cargo.toml"],
+        &["target"],
+        &["target/.rustc_info.json", "target/*/.cargo-lock"],
+    ),
+    (
+        "JavaScript",
+        &["package.json"],
+        &["node_modules", "dist", "out", ".next", ".nuxt", ".svelte-kit", ".turbo", ".parcel-cache", ".cache", ".output", ".nyc_output"],
+        &[
+            "node_modules/.package-lock.json",
+            "node_modules/.yarn-integrity",
+            "node_modules/.yarn-state.yml",
+            "node_modules/.modules.yaml",
+            ".next/build-manifest.json",
+            ".next/BUILD_ID",
+            ".nuxt/nuxt.d.ts",
+            ".svelte-kit/ambient.d.ts",
+            ".parcel-cache/lock.mdb",
+            ".nyc_output/processinfo",
+        ],
+    ),
+    (
+        "Python",
+        &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"],
+        &["__pycache__", ".eggs", "eggs", "build", "dist", ".pytest_cache", ".mypy_cache", ".ruff_cache", ".tox"],
+        &[
+            "__pycache__/*.pyc",
+            ".eggs/README.txt",
+            "build/lib*",
+            "build/bdist.*",
+            "dist/*.whl",
+            "dist/*.tar.gz",
+            ".pytest_cache/v",
+            ".tox/.tox-info.json",
+            ".tox/*/.tox-info.json",
+        ],
+    ),
+    // Go keeps its build cache outside the project; the rule only labels projects
+    ("Go", &["go.mod"], &[], &[]),
+    (
+        "C/C++",
+        &["Makefile", "CMakeLists.txt", "configure.ac", "meson.build"],
+        &["build", ".build", "cmake-build-debug", "cmake-build-release", "Debug", "Release", "out"],
+        &[
+            "build/CMakeCache.txt",
+            "build/build.ninja",
+            "build/meson-info",
+            "build/config.status",
+            ".build/CMakeCache.txt",
+            "cmake-build-debug/CMakeCache.txt",
+            "cmake-build-release/CMakeCache.txt",
+            "out/CMakeCache.txt",
+            "out/build/*/CMakeCache.txt",
+            "Debug/*.tlog",
+            "Release/*.tlog",
+        ],
+    ),
+    ("Java", &["pom.xml"], &["target"], &["target/maven-status", "target/maven-archiver"]),
+    (
+        "Java",
+        &["build.gradle", "settings.gradle"],
+        &["build", ".gradle"],
+        &["build/tmp", "build/classes", ".gradle/buildOutputCleanup"],
+    ),
+    ("PHP", &["composer.json"], &["vendor"], &["vendor/autoload.php", "vendor/composer/installed.json"]),
+    ("Ruby", &["Gemfile"], &[".bundle", "vendor/bundle"], &["vendor/bundle/ruby"]),
+    ("Swift", &["Package.swift"], &[".build"], &[".build/workspace-state.json"]),
+    (
+        "Kotlin",
+        &["build.gradle.kts", "settings.gradle.kts"],
+        &["build", ".gradle"],
+        &["build/tmp", "build/classes", ".gradle/buildOutputCleanup"],
+    ),
+    ("Scala", &["build.sbt"], &["target", "project/target"], &["target/streams", "target/scala-*", "project/target/streams"]),
+    ("Haskell", &["stack.yaml"], &[".stack-work"], &[".stack-work/install", ".stack-work/dist"]),
+    ("Haskell", &["*.cabal", "cabal.project"], &["dist-newstyle"], &["dist-newstyle/cache/plan.json"]),
+    ("Elixir", &["mix.exs"], &["_build", "deps"], &["_build/*/lib", "deps/*/.hex", "deps/*/.fetch"]),
+    (".NET", &["*.csproj", "*.fsproj", "*.sln"], &["bin", "obj"], &["obj/project.assets.json", "bin/Debug", "bin/Release"]),
+    ("Dart", &["pubspec.yaml"], &[".dart_tool", "build"], &[".dart_tool/package_config.json", "build/flutter_assets"]),
+];
+
+pub fn builtin_rules() -> Vec<Rule> {
+    BUILTIN_RULES
+        .iter()
+        .map(|(ecosystem, markers, artifacts, fingerprints)| Rule {
+            ecosystem: ecosystem.to_string(),
+            markers: markers.iter().map(|m| m.to_string()).collect(),
+            artifacts: artifacts.iter().map(|a| a.to_string()).collect(),
+            fingerprints: fingerprints.iter().map(|f| f.to_string()).collect(),
+        })
+        .collect()
+}
+
+/// An artifact directory found next to a project marker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtifactMatch {
+    pub path: PathBuf,
+    /// A fingerprint proved the directory was written by the build tool.
+    pub verified: bool,
+}
+
+enum Marker {
+    File(String),
+    Pattern(Pattern),
+}
+
+struct CompiledRule {
+    rule: Rule,
+    markers: Vec<Marker>,
+}
+
+/// User rules followed by the built-in ones. The first matching rule names
+/// the project's language.
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    pub fn new(user_rules: &[Rule]) -> Self {
+        let rules = user_rules
+            .iter()
+            .cloned()
+            .chain(builtin_rules())
+            .map(|rule| {
+                let markers = rule
+                    .markers
+                    .iter()
+                    .map(|marker| match Pattern::new(marker) {
+                        Ok(pattern) if marker.contains(['*', '?', '[']) => Marker::Pattern(pattern),
+                        _ => Marker::File(marker.clone()),
+                    })
+                    .collect();
+                CompiledRule { rule, markers }
+            })
+            .collect();
+        RuleSet { rules }
+    }
+
+    fn matching(&self, dir: &Path) -> Vec<&Rule> {
+        // Only list the directory when a glob marker needs it
+        let mut names: Option<Vec<String>> = None;
+        self.rules
+            .iter()
+            .filter(|compiled| {
+                compiled.markers.iter().any(|marker| match marker {
+                    Marker::File(name) => dir.join(name).is_file(),
+                    Marker::Pattern(pattern) => names
+                        .get_or_insert_with(|| {
+                            fs::read_dir(dir)
+                                .map(|entries| {
+                                    entries.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().into_owned()).collect()
+                                })
+                                .unwrap_or_default()
+                        })
+                        .iter()
+                        .any(|name| pattern.matches(name)),
+                })
+            })
+            .map(|compiled| &compiled.rule)
+            .collect()
+    }
+
+    /// The ecosystem of the first rule whose markers are present in `dir`.
+    pub fn language_for(&self, dir: &Path) -> Option<&str> {
+        self.matching(dir).first().map(|rule| rule.ecosystem.as_str())
+    }
+
+    /// Which rule claims `artifact` for the project at `dir`, e.g. "Rust: target".
+    pub fn rule_for(&self, dir: &Path, artifact: &Path) -> Option<String> {
+        self.matching(dir).into_iter().find_map(|rule| {
+            rule.artifacts
+                .iter()
+                .find(|relative| dir.join(relative) == artifact)
+                .map(|relative| format!("{}: {}", rule.ecosystem, relative))
+        })
+    }
+
+    /// Existing artifact directories of every rule matching the project at `dir`.
+    /// A directory claimed by several rules is verified if any of them verifies it.
+    pub fn artifacts_in(&self, dir: &Path) -> Vec<ArtifactMatch> {
+        let mut found: Vec<ArtifactMatch> = vec![];
+        for rule in self.matching(dir) {
+            for artifact in &rule.artifacts {
+                let path = dir.join(artifact);
+                if !path.is_dir() {
+                    continue;
+                }
+                let verified = rule.verifies(dir, artifact);
+                match found.iter_mut().find(|m| m.path == path) {
+                    Some(existing) => existing.verified |= verified,
+                    None => found.push(ArtifactMatch { path, verified }),
+                }
+            }
+        }
+        found
+    }
+}
@@ -0,0 +1,96 @@
+// Disk usage
+// What a file really occupies: allocated blocks, and hardlinks counted once
+
+use crate::db::store::LinkedFile;
+use std::fs::Metadata;
+use std::path::Path;
+
+/// Size of a filesystem and what is left of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskSpace {
+    /// Tells filesystems apart, as `device_of` does.
+    pub device: u64,
+    /// Free bytes usable without root privileges.
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl DiskSpace {
+    /// Share of the filesystem still available, in percent.
+    pub fn free_percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 100.0;
+        }
+        self.available_bytes as f64 * 100.0 / self.total_bytes as f64
+    }
+}
+
+/// Bytes allocated to the file. Sparse files take less than their length;
+/// outside Unix the length is all we know.
+pub fn allocated_bytes(metadata: &Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::MetadataExt::blocks(metadata) * 512
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// The identity of a file with several hard links, so its blocks are only
+/// counted for the first link a scan sees.
+pub fn linked_file(metadata: &Metadata) -> Option<LinkedFile> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.nlink() > 1).then(|| LinkedFile {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            disk_bytes: allocated_bytes(metadata),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Space on the filesystem holding `path`, where the platform tells us.
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        let device = device_of(&std::fs::metadata(path).ok()?)?;
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: statvfs only writes into `stats`, and `c_path` is NUL-terminated
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return None;
+        }
+        // Field widths differ between platforms
+        #[allow(clippy::unnecessary_cast)]
+        let (fragment, available, blocks) = (stats.f_frsize as u64, stats.f_bavail as u64, stats.f_blocks as u64);
+        Some(DiskSpace { device, available_bytes: available * fragment, total_bytes: blocks * fragment })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// The filesystem a file lives on, where the platform tells us.
+pub fn device_of(metadata: &Metadata) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        Some(std::os::unix::fs::MetadataExt::dev(metadata))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
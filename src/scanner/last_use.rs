@@ -0,0 +1,42 @@
+// Usage signals
+// When a project's own files and history last changed, for retention by last use
+
+use chrono::{DateTime, Utc};
+use std::fs::Metadata;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::UNIX_EPOCH;
+
+/// Modification time in nanoseconds since the epoch, 0 when unavailable.
+pub fn mtime_ns(metadata: &Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos() as i64)
+}
+
+/// A time from `mtime_ns`, None for the 0 of an unknown time.
+pub fn from_ns(ns: i64) -> Option<DateTime<Utc>> {
+    Some(DateTime::from_timestamp_nanos(ns)).filter(|_| ns > 0)
+}
+
+/// Time of the last commit touching `project`, None when it is not inside a
+/// git work tree or git is not installed.
+pub fn last_commit(project: &Path) -> Option<DateTime<Utc>> {
+    // Spare a process per project outside any repository
+    project.ancestors().find(|dir| dir.join(".git").exists())?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(project)
+        .args(["log", "-1", "--format=%ct", "--", "."])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let seconds = String::from_utf8_lossy(&output.stdout).trim().parse::<i64>().ok()?;
+    DateTime::from_timestamp(seconds, 0)
+}
@@ -0,0 +1,462 @@
+// Artifact scanning
+// Shared by the TUI and the headless CLI
+
+use crate::cleanup::quarantine::QUARANTINE_DIR;
+use crate::config::Config;
+use crate::db::store::{DirSizeEntry, DiskUsage, UsageSignals};
+use crate::rules::RuleSet;
+use crate::rules::cachedir::read_cachedir_tag;
+use crate::tracking::logger::BuildLogger;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::utils::format_size;
+
+pub mod disk_usage;
+pub mod last_use;
+pub mod progress;
+pub mod size_cache;
+
+pub use progress::ScanProgress;
+pub use size_cache::SizeCache;
+
+#[derive(Debug, Clone)]
+pub struct FoundArtifact {
+    pub path: String,
+    pub project_path: String,
+    pub language: String,
+    /// Sum of file lengths.
+    pub size: u64,
+    /// Allocated blocks, with files hardlinked elsewhere in the scan counted once.
+    pub disk_size: u64,
+    /// Confirmed by a rule fingerprint; unverified artifacts are never purged automatically.
+    pub verified: bool,
+    /// The comment line of the directory's CACHEDIR.TAG, if it has one.
+    pub description: Option<String>,
+    /// When the artifact and its project were last touched.
+    pub signals: UsageSignals,
+}
+
+/// Language shown for tagged cache directories outside any known project.
+pub const CACHE_LANGUAGE: &str = "Cache";
+
+/// Version control internals: never projects, and often large.
+pub const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn", ".bzr", ".jj", "_darcs", ".pijul"];
+
+/// A directory to scan, how many levels below it projects are looked for,
+/// and whether the walk may leave it through symlinks or mount points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanRoot {
+    pub path: String,
+    pub max_depth: usize,
+    pub follow_symlinks: bool,
+    pub one_file_system: bool,
+}
+
+impl ScanRoot {
+    /// Pairs each path with its configured depth and boundary policies.
+    pub fn from_config(paths: &[String], config: &Config) -> Vec<ScanRoot> {
+        paths
+            .iter()
+            .map(|path| ScanRoot {
+                path: path.clone(),
+                max_depth: config.depth_for(path),
+                follow_symlinks: config.follow_symlinks,
+                one_file_system: config.one_file_system,
+            })
+            .collect()
+    }
+}
+
+/// Walks every scan root looking for projects matched by `rules` and for
+/// directories carrying a CACHEDIR.TAG, and returns the artifacts found, sorted by path.
+/// Roots and their subtrees are walked in parallel. Blocking; progress lines are reported
+/// through `on_log` and counters through `progress`. A project is found when its directory
+/// is at most `max_depth` levels below the root. Unchanged directories are sized from `cache`,
+/// which collects fresh entries for the caller to save.
+pub fn scan(
+    roots: &[ScanRoot],
+    excluded_paths: &[String],
+    rules: &RuleSet,
+    cache: &SizeCache,
+    progress: &ScanProgress,
+    on_log: impl Fn(String) + Sync,
+) -> Vec<FoundArtifact> {
+    on_log("Starting scan...".to_string());
+    let found = Mutex::new(vec![]);
+    let seen_links = Mutex::new(HashSet::new());
+    roots.par_iter().for_each(|root| {
+        if progress.is_cancelled() {
+            return;
+        }
+        on_log(format!("Scanning path: {} (depth {})", root.path, root.max_depth));
+        let device = match fs::metadata(&root.path) {
+            Ok(metadata) if root.one_file_system => disk_usage::device_of(&metadata),
+            _ => None,
+        };
+        let walker = Walker {
+            rules,
+            excluded_paths,
+            cache,
+            progress,
+            seen_links: &seen_links,
+            root,
+            device,
+            artifact_dirs: Mutex::new(HashSet::new()),
+            followed: Mutex::new(HashSet::new()),
+            skipped_links: AtomicU64::new(0),
+            skipped_mounts: AtomicU64::new(0),
+            found: Mutex::new(vec![]),
+        };
+        progress.queue_dirs(1);
+        walker.visit(Path::new(&root.path), 0);
+        let skipped_links = walker.skipped_links.into_inner();
+        let skipped_mounts = walker.skipped_mounts.into_inner();
+        if skipped_links > 0 || skipped_mounts > 0 {
+            on_log(format!(
+                "Skipped {} symlinked directories and {} directories on other filesystems in {}.",
+                skipped_links, skipped_mounts, root.path
+            ));
+        }
+        let mut root_found = walker.found.into_inner().unwrap();
+        on_log(format!("Scan complete for {}. Found {} artifacts.", root.path, root_found.len()));
+        found.lock().unwrap().append(&mut root_found);
+    });
+
+    let mut found = found.into_inner().unwrap();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    // Overlapping scan paths report the same artifact more than once
+    found.dedup_by(|a, b| a.path == b.path);
+    let snapshot = progress.snapshot();
+    let stats = format!("{} directories in {:.1}s", snapshot.dirs_visited, snapshot.elapsed.as_secs_f64());
+    if snapshot.sized_dirs > 0 {
+        on_log(format!(
+            "Sizes of {} of {} artifact directories ({}) served from cache.",
+            snapshot.cached_dirs,
+            snapshot.sized_dirs,
+            format_size(snapshot.cached_bytes)
+        ));
+    }
+    if snapshot.cancelled {
+        on_log(format!("Scan cancelled. Found {} artifacts ({}).", found.len(), stats));
+    } else {
+        on_log(format!("Total scan complete. Found {} artifacts ({}).", found.len(), stats));
+    }
+    found
+}
+
+/// Sizes one artifact outside a scan, such as after the watcher saw it change,
+/// serving unchanged directories from `cache`. None when the artifact is gone.
+pub fn measure(path: &Path, cache: &SizeCache, one_file_system: bool) -> Option<DiskUsage> {
+    let metadata = fs::metadata(path).ok().filter(|metadata| metadata.is_dir())?;
+    let rules = RuleSet::new(&[]);
+    let progress = ScanProgress::new();
+    let seen_links = Mutex::new(HashSet::new());
+    let root = ScanRoot { path: path.display().to_string(), max_depth: 0, follow_symlinks: false, one_file_system };
+    let walker = Walker {
+        rules: &rules,
+        excluded_paths: &[],
+        cache,
+        progress: &progress,
+        seen_links: &seen_links,
+        root: &root,
+        device: if one_file_system { disk_usage::device_of(&metadata) } else { None },
+        artifact_dirs: Mutex::new(HashSet::new()),
+        followed: Mutex::new(HashSet::new()),
+        skipped_links: AtomicU64::new(0),
+        skipped_mounts: AtomicU64::new(0),
+        found: Mutex::new(vec![]),
+    };
+    walker.size_of(path).map(|(usage, _)| usage)
+}
+
+/// State for walking one scan root.
+struct Walker<'a> {
+    rules: &'a RuleSet,
+    excluded_paths: &'a [String],
+    cache: &'a SizeCache,
+    progress: &'a ScanProgress,
+    /// Hardlinked files already counted anywhere in the scan, by device and inode.
+    seen_links: &'a Mutex<HashSet<(u64, u64)>>,
+    root: &'a ScanRoot,
+    /// The root's filesystem when the walk must stay on it.
+    device: Option<u64>,
+    /// Artifacts already claimed by a project; never searched for nested projects.
+    artifact_dirs: Mutex<HashSet<PathBuf>>,
+    /// Targets of symlinks already followed, so link loops end.
+    followed: Mutex<HashSet<PathBuf>>,
+    skipped_links: AtomicU64,
+    skipped_mounts: AtomicU64,
+    found: Mutex<Vec<FoundArtifact>>,
+}
+
+impl Walker<'_> {
+    /// Looks for projects and caches in `dir` and below. Returns the newest
+    /// modification time of the source files on the way: files in the directories
+    /// visited, leaving out artifacts, tagged caches, version control internals and
+    /// quarantine areas. A project's source time is known once the walk below it is
+    /// done, so nested projects are never walked twice, and it follows the root's
+    /// depth, symlink, filesystem and exclusion policies.
+    fn visit(&self, dir: &Path, depth: usize) -> i64 {
+        self.progress.visit_dir();
+        if self.progress.is_cancelled() {
+            return 0;
+        }
+
+        // A valid CACHEDIR.TAG marks the directory itself as an artifact, whatever its name
+        if depth > 0
+            && let Some(tag) = read_cachedir_tag(dir)
+        {
+            let project = dir.parent().unwrap_or(Path::new("."));
+            let language = self.rules.language_for(project).unwrap_or(CACHE_LANGUAGE).to_string();
+            // Only the cache's own files tell when it was used
+            self.add(dir, project, language, true, tag.description, UsageSignals::default());
+            return 0;
+        }
+
+        let project = self.rules.language_for(dir).map(|language| {
+            let artifacts = self.rules.artifacts_in(dir);
+            self.artifact_dirs.lock().unwrap().extend(artifacts.iter().map(|a| a.path.clone()));
+            (language, artifacts)
+        });
+
+        let mut newest = 0;
+        let mut subdirs = vec![];
+        for entry in fs::read_dir(dir).into_iter().flatten().filter_map(|e| e.ok()) {
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_file()
+                && let Ok(metadata) = entry.metadata()
+            {
+                newest = newest.max(last_use::mtime_ns(&metadata));
+            } else if depth < self.root.max_depth
+                && (file_type.is_dir() || (file_type.is_symlink() && entry.path().is_dir()))
+                && !VCS_DIRS.iter().any(|name| entry.file_name() == *name)
+                && entry.file_name() != QUARANTINE_DIR
+            {
+                subdirs.push(entry.path());
+            }
+        }
+        let subdirs: Vec<PathBuf> = {
+            // Never look for projects inside an artifact, e.g. packages in node_modules
+            let artifact_dirs = self.artifact_dirs.lock().unwrap();
+            subdirs
+                .into_iter()
+                .filter(|path| !self.is_excluded(path) && self.may_enter(path) && !artifact_dirs.contains(path))
+                .collect()
+        };
+        self.progress.queue_dirs(subdirs.len());
+        let below = subdirs.par_iter().map(|subdir| self.visit(subdir, depth + 1)).max().unwrap_or(0);
+        newest = newest.max(below);
+
+        if let Some((language, artifacts)) = project {
+            let signals = if artifacts.is_empty() {
+                UsageSignals::default()
+            } else {
+                UsageSignals {
+                    content_modified: None,
+                    source_modified: last_use::from_ns(newest),
+                    last_commit: last_use::last_commit(dir),
+                }
+            };
+            artifacts.into_par_iter().filter(|artifact| self.may_enter(&artifact.path)).for_each(|artifact| {
+                let tag = read_cachedir_tag(&artifact.path);
+                self.add(
+                    &artifact.path,
+                    dir,
+                    language.to_string(),
+                    artifact.verified || tag.is_some(),
+                    tag.and_then(|tag| tag.description),
+                    signals,
+                );
+            });
+        }
+        newest
+    }
+
+    /// Whether `path` is, or lies inside, an excluded path.
+    fn is_excluded(&self, path: &Path) -> bool {
+        let path = path.display().to_string();
+        self.excluded_paths.iter().any(|ex| path.contains(ex))
+    }
+
+    /// Applies the symlink and filesystem policies to a directory below the root.
+    fn may_enter(&self, path: &Path) -> bool {
+        let Ok(metadata) = fs::symlink_metadata(path) else { return false };
+        if metadata.file_type().is_symlink() {
+            if !self.root.follow_symlinks {
+                self.skipped_links.fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+            let first_visit = fs::canonicalize(path).is_ok_and(|target| self.followed.lock().unwrap().insert(target));
+            if !first_visit {
+                return false;
+            }
+        }
+        if let Some(device) = self.device
+            && fs::metadata(path).ok().and_then(|m| disk_usage::device_of(&m)) != Some(device)
+        {
+            self.skipped_mounts.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    /// Records an artifact with its size and the project's `signals`, completed
+    /// with the newest file time inside it. That time always comes from files
+    /// stat'ed by this scan, never from a cache entry alone, so an artifact being
+    /// rewritten in place is never taken for unused.
+    fn add(&self, path: &Path, project: &Path, language: String, verified: bool, description: Option<String>, signals: UsageSignals) {
+        if self.is_excluded(path) {
+            return;
+        }
+        let path_str = path.display().to_string();
+        // Interrupted by a cancel: the size would be wrong, so leave it out
+        let Some((usage, newest_mtime_ns)) = self.size_of(path) else { return };
+        self.progress.find_artifact();
+        self.found.lock().unwrap().push(FoundArtifact {
+            path: path_str,
+            project_path: project.display().to_string(),
+            language,
+            size: usage.apparent_bytes,
+            disk_size: usage.disk_bytes,
+            verified,
+            description,
+            signals: UsageSignals { content_modified: last_use::from_ns(newest_mtime_ns), ..signals },
+        });
+    }
+
+    /// Total size of the files under `path` and their newest modification time,
+    /// or None when cancelled. Fresh cache entries are handed to the cache once
+    /// the whole artifact is sized.
+    fn size_of(&self, path: &Path) -> Option<(DiskUsage, i64)> {
+        let mut entries = vec![];
+        // Only reached for a symlinked artifact when links are followed; size its target
+        let target = match fs::read_link(path) {
+            Ok(_) => fs::canonicalize(path).ok()?,
+            Err(_) => path.to_path_buf(),
+        };
+        let measured = self.dir_size(&target, &mut entries)?;
+        self.cache.update(path.display().to_string(), entries);
+        Some(measured)
+    }
+
+    /// Sizes `dir` and everything below it and finds the newest modification time
+    /// there. Directories whose signature is unchanged since the cached entry was
+    /// written are not listed again, but their files are still stat'ed, so files
+    /// rewritten in place are counted with their current size and time.
+    fn dir_size(&self, dir: &Path, entries: &mut Vec<DirSizeEntry>) -> Option<(DiskUsage, i64)> {
+        if self.progress.is_cancelled() {
+            return None;
+        }
+        let Ok(metadata) = fs::symlink_metadata(dir) else { return Some((DiskUsage::default(), 0)) };
+        // Like du -x: a filesystem mounted inside an artifact is not part of it
+        if self.device.is_some() && disk_usage::device_of(&metadata) != self.device {
+            return Some((DiskUsage::default(), 0));
+        }
+        let path = dir.display().to_string();
+        let restated = self.cache.lookup(&path, &metadata).and_then(|cached| restat(dir, cached));
+        let entry = match restated {
+            Some(entry) => {
+                self.progress.size_dir(entry.size_bytes, true);
+                entry
+            }
+            None => {
+                let (inode, mtime_ns) = size_cache::dir_signature(&metadata);
+                let mut entry = DirSizeEntry {
+                    path,
+                    inode,
+                    mtime_ns,
+                    size_bytes: 0,
+                    disk_bytes: 0,
+                    linked: vec![],
+                    subdirs: vec![],
+                    files: vec![],
+                    newest_mtime_ns: mtime_ns,
+                };
+                for child in fs::read_dir(dir).into_iter().flatten().filter_map(|e| e.ok()) {
+                    let Ok(file_type) = child.file_type() else { continue };
+                    if file_type.is_dir() {
+                        entry.subdirs.push(child.file_name().to_string_lossy().into_owned());
+                    } else if file_type.is_file()
+                        && let Ok(metadata) = child.metadata()
+                    {
+                        entry.files.push(child.file_name().to_string_lossy().into_owned());
+                        add_file(&mut entry, &metadata);
+                    }
+                }
+                self.progress.size_dir(entry.size_bytes, false);
+                entry
+            }
+        };
+        let mut total = DiskUsage { apparent_bytes: entry.size_bytes, disk_bytes: entry.disk_bytes };
+        let mut newest = entry.newest_mtime_ns;
+        {
+            let mut seen_links = self.seen_links.lock().unwrap();
+            for linked in &entry.linked {
+                if seen_links.insert((linked.device, linked.inode)) {
+                    total.disk_bytes += linked.disk_bytes;
+                }
+            }
+        }
+        for name in &entry.subdirs {
+            let (usage, subdir_newest) = self.dir_size(&dir.join(name), entries)?;
+            total.add(usage);
+            newest = newest.max(subdir_newest);
+        }
+        entries.push(entry);
+        Some((total, newest))
+    }
+}
+
+/// Counts one regular file directly inside the entry's directory.
+fn add_file(entry: &mut DirSizeEntry, metadata: &fs::Metadata) {
+    entry.size_bytes += metadata.len();
+    entry.newest_mtime_ns = entry.newest_mtime_ns.max(last_use::mtime_ns(metadata));
+    match disk_usage::linked_file(metadata) {
+        Some(linked) => entry.linked.push(linked),
+        None => entry.disk_bytes += disk_usage::allocated_bytes(metadata),
+    }
+}
+
+/// The cached entry for `dir` with its files stat'ed again, or None when one of
+/// them is gone or no longer a regular file and the directory must be listed.
+fn restat(dir: &Path, cached: &DirSizeEntry) -> Option<DirSizeEntry> {
+    let mut entry = DirSizeEntry {
+        size_bytes: 0,
+        disk_bytes: 0,
+        linked: vec![],
+        newest_mtime_ns: cached.mtime_ns,
+        ..cached.clone()
+    };
+    for name in &cached.files {
+        let metadata = fs::symlink_metadata(dir.join(name)).ok().filter(|metadata| metadata.is_file())?;
+        add_file(&mut entry, &metadata);
+    }
+    Some(entry)
+}
+
+/// Records scan results in the store. Returns how many writes failed.
+pub async fn record(logger: &BuildLogger, artifacts: &[FoundArtifact]) -> usize {
+    let mut failures = 0;
+    for artifact in artifacts {
+        if logger
+            .log_build(
+                &artifact.project_path,
+                &artifact.language,
+                &artifact.path,
+                DiskUsage { apparent_bytes: artifact.size, disk_bytes: artifact.disk_size },
+                artifact.verified,
+                artifact.description.as_deref(),
+            )
+            .await
+            .is_err()
+            || logger.store.record_signals(&artifact.path, &artifact.signals).await.is_err()
+        {
+            failures += 1;
+        }
+    }
+    failures
+}
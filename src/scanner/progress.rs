@@ -0,0 +1,111 @@
+// Scan progress
+// Counters shared between scan workers and whoever displays them
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Live counters for one scan, plus its cancel flag. Cancelling stops workers
+/// at the next directory; whatever was found so far is still returned.
+#[derive(Debug)]
+pub struct ScanProgress {
+    dirs_queued: AtomicU64,
+    dirs_visited: AtomicU64,
+    bytes_counted: AtomicU64,
+    sized_dirs: AtomicU64,
+    cached_dirs: AtomicU64,
+    cached_bytes: AtomicU64,
+    artifacts_found: AtomicU64,
+    cancelled: AtomicBool,
+    started: Instant,
+}
+
+/// A point-in-time copy of the counters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressSnapshot {
+    pub dirs_visited: u64,
+    /// Directories discovered but not visited yet.
+    pub dirs_pending: u64,
+    pub bytes_counted: u64,
+    /// Directories inside artifacts whose size was counted.
+    pub sized_dirs: u64,
+    /// How many of those, and of their bytes, came from the size cache.
+    pub cached_dirs: u64,
+    pub cached_bytes: u64,
+    pub artifacts_found: u64,
+    pub elapsed: Duration,
+    /// Time left at the current rate, once anything has been visited.
+    /// Grows while new subtrees are discovered.
+    pub eta: Option<Duration>,
+    pub cancelled: bool,
+}
+
+impl Default for ScanProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanProgress {
+    pub fn new() -> Self {
+        ScanProgress {
+            dirs_queued: AtomicU64::new(0),
+            dirs_visited: AtomicU64::new(0),
+            bytes_counted: AtomicU64::new(0),
+            sized_dirs: AtomicU64::new(0),
+            cached_dirs: AtomicU64::new(0),
+            cached_bytes: AtomicU64::new(0),
+            artifacts_found: AtomicU64::new(0),
+            cancelled: AtomicBool::new(false),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn queue_dirs(&self, count: usize) {
+        self.dirs_queued.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn visit_dir(&self) {
+        self.dirs_visited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts one directory of an artifact holding `bytes` of files.
+    pub(crate) fn size_dir(&self, bytes: u64, cached: bool) {
+        self.sized_dirs.fetch_add(1, Ordering::Relaxed);
+        self.bytes_counted.fetch_add(bytes, Ordering::Relaxed);
+        if cached {
+            self.cached_dirs.fetch_add(1, Ordering::Relaxed);
+            self.cached_bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn find_artifact(&self) {
+        self.artifacts_found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let dirs_visited = self.dirs_visited.load(Ordering::Relaxed);
+        let dirs_pending = self.dirs_queued.load(Ordering::Relaxed).saturating_sub(dirs_visited);
+        let elapsed = self.started.elapsed();
+        let eta = (dirs_visited > 0).then(|| elapsed.mul_f64(dirs_pending as f64 / dirs_visited as f64));
+        ProgressSnapshot {
+            dirs_visited,
+            dirs_pending,
+            bytes_counted: self.bytes_counted.load(Ordering::Relaxed),
+            sized_dirs: self.sized_dirs.load(Ordering::Relaxed),
+            cached_dirs: self.cached_dirs.load(Ordering::Relaxed),
+            cached_bytes: self.cached_bytes.load(Ordering::Relaxed),
+            artifacts_found: self.artifacts_found.load(Ordering::Relaxed),
+            elapsed,
+            eta,
+            cancelled: self.is_cancelled(),
+        }
+    }
+}
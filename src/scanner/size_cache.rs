@@ -0,0 +1,60 @@
+// Directory size cache
+// Lets rescans skip listing directories whose inode and mtime have not changed
+
+use super::last_use::mtime_ns;
+use crate::db::store::{ArtifactStore, DirSizeEntry};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::sync::Mutex;
+
+/// Entries loaded before a scan, and the ones the scan computed for each
+/// artifact it finished sizing.
+#[derive(Debug, Default)]
+pub struct SizeCache {
+    previous: HashMap<String, DirSizeEntry>,
+    updated: Mutex<Vec<(String, Vec<DirSizeEntry>)>>,
+}
+
+impl SizeCache {
+    pub fn new(entries: Vec<DirSizeEntry>) -> Self {
+        SizeCache {
+            previous: entries.into_iter().map(|entry| (entry.path.clone(), entry)).collect(),
+            updated: Mutex::new(vec![]),
+        }
+    }
+
+    /// Loads the cache from the store. A store error just means a full walk.
+    pub async fn load(store: &dyn ArtifactStore) -> Self {
+        Self::new(store.dir_size_cache().await.unwrap_or_default())
+    }
+
+    /// The cached entry for `path` if the directory itself is unchanged since.
+    /// Its files may not be; the caller stats them again.
+    pub fn lookup(&self, path: &str, metadata: &Metadata) -> Option<&DirSizeEntry> {
+        let (inode, mtime_ns) = dir_signature(metadata);
+        self.previous.get(path).filter(|entry| entry.inode == inode && entry.mtime_ns == mtime_ns)
+    }
+
+    /// Keeps the entries computed for a fully sized artifact.
+    pub fn update(&self, artifact: String, entries: Vec<DirSizeEntry>) {
+        self.updated.lock().unwrap().push((artifact, entries));
+    }
+
+    /// Replaces the stored entries of every artifact sized by the scan.
+    pub async fn save(self, store: &dyn ArtifactStore) -> Result<(), sqlx::Error> {
+        let updated = self.updated.into_inner().unwrap();
+        let roots: Vec<String> = updated.iter().map(|(artifact, _)| artifact.clone()).collect();
+        let entries: Vec<DirSizeEntry> = updated.into_iter().flat_map(|(_, entries)| entries).collect();
+        store.replace_dir_sizes(&roots, &entries).await
+    }
+}
+
+/// The inode and modification time identifying a directory's current contents.
+/// Inodes are only available on Unix; elsewhere the mtime alone is used.
+pub fn dir_signature(metadata: &Metadata) -> (u64, i64) {
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+    #[cfg(not(unix))]
+    let inode = 0;
+    (inode, mtime_ns(metadata))
+}
@@ -0,0 +1,274 @@
+// CLI output tests
+
+use crate::cleanup::plan::PlannedArtifact;
+use crate::cleanup::policy::Signal;
+use crate::cli::output::{
+    ArtifactRow, LanguageStats, OutputFormat, Plan, SCHEMA_VERSION, Stats, write_artifacts, write_deletions, write_pins,
+    write_plan, write_run_stream, write_run_stream_header, write_runs, write_stats, write_activity,
+};
+use crate::db::store::{BuildActivity, DaemonRun, DeletionRecord, Pin};
+use chrono::{TimeZone, Utc};
+
+fn sample_row() -> ArtifactRow {
+    let time = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    ArtifactRow {
+        path: "/work/app/target".to_string(),
+        project_path: "/work/app".to_string(),
+        language: "Rust".to_string(),
+        size_bytes: 2048,
+        last_modified: None,
+        first_seen: time,
+        last_seen: time,
+        verified: true,
+        description: None,
+        disk_bytes: Some(4096),
+        pinned: true,
+        pin_note: Some("release".to_string()),
+        pin_expires_at: None,
+    }
+}
+
+#[test]
+fn test_json_artifacts_envelope() {
+    let mut out = Vec::new();
+    write_artifacts(&mut out, OutputFormat::Json, &[sample_row()]).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["schema_version"], SCHEMA_VERSION);
+    assert_eq!(value["kind"], "artifacts");
+    assert_eq!(value["data"][0]["path"], "/work/app/target");
+    assert_eq!(value["data"][0]["project_path"], "/work/app");
+    assert_eq!(value["data"][0]["size_bytes"], 2048);
+    assert!(value["data"][0]["last_modified"].is_null());
+    assert_eq!(value["data"][0]["first_seen"], "2025-01-02T03:04:05Z");
+    assert_eq!(value["data"][0]["verified"], true);
+    assert_eq!(value["data"][0]["disk_bytes"], 4096);
+    assert_eq!(value["data"][0]["pinned"], true);
+}
+
+#[test]
+fn test_csv_artifacts_columns() {
+    let mut out = Vec::new();
+    write_artifacts(&mut out, OutputFormat::Csv, &[sample_row()]).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next(),
+        Some("path,project_path,language,size_bytes,last_modified,first_seen,last_seen,verified,description,disk_bytes,pinned,pin_note,pin_expires_at")
+    );
+    let row = lines.next().unwrap();
+    assert!(row.starts_with("/work/app/target,/work/app,Rust,2048,,"));
+    assert!(row.ends_with(",true,,4096,true,release,"));
+}
+
+#[test]
+fn test_csv_empty_still_has_header() {
+    let mut out = Vec::new();
+    write_artifacts(&mut out, OutputFormat::Csv, &[]).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "path,project_path,language,size_bytes,last_modified,first_seen,last_seen,verified,description,disk_bytes,pinned,pin_note,pin_expires_at\n"
+    );
+}
+
+#[test]
+fn test_json_stats() {
+    let stats = Stats {
+        artifacts: 1,
+        total_size_bytes: 2048,
+        total_disk_bytes: 4096,
+        build_events: 3,
+        languages: vec![LanguageStats { language: "Rust".to_string(), artifacts: 1, size_bytes: 2048, disk_bytes: 4096 }],
+    };
+    let mut out = Vec::new();
+    write_stats(&mut out, OutputFormat::Json, &stats).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["kind"], "stats");
+    assert_eq!(value["data"]["build_events"], 3);
+    assert_eq!(value["data"]["languages"][0]["language"], "Rust");
+    assert_eq!(value["data"]["total_disk_bytes"], 4096);
+}
+
+fn sample_plan() -> Plan {
+    let artifact = PlannedArtifact {
+        path: "/work/app/target".to_string(),
+        project_path: "/work/app".to_string(),
+        language: "Rust".to_string(),
+        size_bytes: 2048,
+        disk_bytes: 4096,
+        first_seen: Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap(),
+        age_days: 40,
+        rule: "Rust: target".to_string(),
+        verified: true,
+        last_used: Utc.with_ymd_and_hms(2025, 1, 12, 0, 0, 0).unwrap(),
+        last_used_signal: Signal::LastCommit,
+        idle_days: 30,
+        reason: Some("unused for 30 days (last git commit, 2025-01-12)".to_string()),
+        policy: Some("product".to_string()),
+        selected: true,
+    };
+    Plan { artifacts: vec![artifact], total_size_bytes: 2048, total_disk_bytes: 4096, pinned: 0 }
+}
+
+#[test]
+fn test_plan_outputs() {
+    let mut out = Vec::new();
+    write_plan(&mut out, OutputFormat::Json, &sample_plan()).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["kind"], "plan");
+    assert_eq!(value["data"]["total_disk_bytes"], 4096);
+    assert_eq!(value["data"]["artifacts"][0]["rule"], "Rust: target");
+    assert_eq!(value["data"]["artifacts"][0]["age_days"], 40);
+    assert_eq!(value["data"]["artifacts"][0]["last_used_signal"], "last_commit");
+    assert_eq!(value["data"]["artifacts"][0]["reason"], "unused for 30 days (last git commit, 2025-01-12)");
+    assert_eq!(value["data"]["artifacts"][0]["policy"], "product");
+    assert!(value["data"]["artifacts"][0].get("selected").is_none());
+
+    let mut out = Vec::new();
+    write_plan(&mut out, OutputFormat::Csv, &sample_plan()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().next(), Some("path,project_path,language,size_bytes,disk_bytes,first_seen,age_days,rule,verified,last_used,last_used_signal,idle_days,reason,policy"));
+
+    let mut out = Vec::new();
+    write_plan(&mut out, OutputFormat::Table, &sample_plan()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("Rust: target"));
+    assert!(text.contains("product"));
+    assert!(text.ends_with("1 artifacts, 2.0 KB to free (4.1 KB on disk)\n"));
+}
+
+#[test]
+fn test_deletion_outputs() {
+    let deletion = DeletionRecord {
+        path: "/work/app/target".to_string(),
+        size_bytes: 2048,
+        disk_bytes: None,
+        trigger: "manual".to_string(),
+        user: "alice".to_string(),
+        deleted_at: Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap(),
+        outcome: "partial".to_string(),
+        detail: Some("/work/app/target/x: permission denied".to_string()),
+        reason: None,
+    };
+    let mut out = Vec::new();
+    write_deletions(&mut out, OutputFormat::Json, std::slice::from_ref(&deletion)).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["kind"], "deletions");
+    assert_eq!(value["data"][0]["user"], "alice");
+    assert_eq!(value["data"][0]["deleted_at"], "2025-01-02T03:04:05Z");
+
+    let mut out = Vec::new();
+    write_deletions(&mut out, OutputFormat::Csv, &[]).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "path,size_bytes,disk_bytes,trigger,user,deleted_at,outcome,detail,reason\n");
+
+    let mut out = Vec::new();
+    write_deletions(&mut out, OutputFormat::Table, &[deletion]).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.lines().nth(1).unwrap().starts_with("2025-01-02 03:04  alice"));
+    assert!(text.contains("/work/app/target (/work/app/target/x: permission denied)"));
+}
+
+#[test]
+fn test_pin_outputs() {
+    let pinned_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    let pins = vec![
+        Pin::new("/work/app/target".to_string(), Some("release".to_string()), None, pinned_at),
+        Pin::new("/work/old/target".to_string(), None, Some(7), pinned_at),
+    ];
+    let mut out = Vec::new();
+    write_pins(&mut out, OutputFormat::Json, &pins).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["kind"], "pins");
+    assert_eq!(value["data"][0]["note"], "release");
+    assert_eq!(value["data"][1]["expires_at"], "2025-01-09T03:04:05Z");
+
+    let mut out = Vec::new();
+    write_pins(&mut out, OutputFormat::Csv, &[]).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "path,note,pinned_at,expires_at\n");
+
+    let mut out = Vec::new();
+    write_pins(&mut out, OutputFormat::Table, &pins).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("/work/app/target (release)"));
+    assert!(text.lines().nth(2).unwrap().contains("expired"));
+}
+
+#[test]
+fn test_run_outputs() {
+    let started_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    let run = DaemonRun {
+        started_at,
+        finished_at: started_at,
+        status: "completed".to_string(),
+        artifacts: 12,
+        removed: 2,
+        quarantined: 0,
+        purged: 1,
+        failed: 1,
+        refused: 0,
+        freed_bytes: 1_200_000_000,
+        detail: None,
+    };
+    let mut out = Vec::new();
+    write_runs(&mut out, OutputFormat::Table, std::slice::from_ref(&run)).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "2025-01-02 03:04 completed: 12 artifacts, 2 removed, 0 quarantined, 1 purged, 1.2 GB freed, 1 failed, 0 refused\n"
+    );
+
+    let mut out = Vec::new();
+    write_runs(&mut out, OutputFormat::Json, std::slice::from_ref(&run)).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["kind"], "runs");
+    assert_eq!(value["data"][0]["freed_bytes"], 1_200_000_000u64);
+
+    let mut out = Vec::new();
+    write_runs(&mut out, OutputFormat::Csv, &[]).unwrap();
+    assert!(String::from_utf8(out).unwrap().starts_with("started_at,finished_at,status,"));
+
+    // A daemon left running prints one JSON line per pass
+    let mut out = Vec::new();
+    write_run_stream_header(&mut out, OutputFormat::Json).unwrap();
+    for _ in 0..2 {
+        write_run_stream(&mut out, OutputFormat::Json, &run).unwrap();
+    }
+    let lines: Vec<serde_json::Value> =
+        String::from_utf8(out).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!((lines[1]["kind"].as_str(), lines[1]["data"]["artifacts"].as_u64()), (Some("run"), Some(12)));
+
+    // and CSV rows under a single header
+    let mut out = Vec::new();
+    write_run_stream_header(&mut out, OutputFormat::Csv).unwrap();
+    for _ in 0..2 {
+        write_run_stream(&mut out, OutputFormat::Csv, &run).unwrap();
+    }
+    let csv = String::from_utf8(out).unwrap();
+    assert_eq!(csv.lines().count(), 3);
+    assert_eq!(csv.matches("started_at").count(), 1);
+}
+
+#[test]
+fn test_activity_outputs() {
+    let started_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    let activity = BuildActivity {
+        artifact_path: "/p/a/target".to_string(),
+        started_at,
+        ended_at: started_at + chrono::Duration::minutes(2),
+        events: 40,
+        size_before: Some(1_000_000),
+        size_after: 2_500_000,
+    };
+    let mut out = Vec::new();
+    write_activity(&mut out, OutputFormat::Table, std::slice::from_ref(&activity)).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "2025-01-02 03:04 → 03:06 /p/a/target: 40 changes, +1.5 MB (now 2.5 MB)\n");
+
+    let mut out = Vec::new();
+    write_activity(&mut out, OutputFormat::Json, &[activity]).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["kind"], "activity");
+    assert_eq!(value["data"][0]["size_after"], 2_500_000u64);
+
+    let mut out = Vec::new();
+    write_activity(&mut out, OutputFormat::Csv, &[]).unwrap();
+    assert!(String::from_utf8(out).unwrap().starts_with("artifact_path,started_at,ended_at,"));
+}
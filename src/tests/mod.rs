@@ -5,4 +5,6 @@ pub mod db_tests;
 pub mod tracking_tests;
 pub mod ui_tests;
 pub mod watcher_tests;
-pub mod utils_tests;
\ No newline at end of file
+pub mod scanner_tests;
+pub mod utils_tests;
+pub mod cli_tests;
\ No newline at end of file
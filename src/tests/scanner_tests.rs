@@ -0,0 +1,875 @@
+// Scanner and cleanup tests
+
+use crate::cleanup::audit::{Outcome, TRIGGER_QUARANTINE_EXPIRY, TRIGGER_RETENTION};
+use crate::cleanup::guard::{PathGuard, Refusal};
+use crate::cleanup::plan::DeletionPlan;
+use crate::cleanup::policy::{Limit, PolicyBlock, RetentionPolicy};
+use crate::cleanup::purge_by_policy;
+use crate::cleanup::quarantine::{self, QUARANTINE_DIR, QuarantineError};
+use crate::cleanup::remove::{RemovalFailure, RemovalReport, remove_tree};
+use crate::db::connection::open_store;
+use crate::db::store::{ArtifactRecord, Pin, UsageSignals};
+use crate::rules::{Rule, RuleSet};
+use crate::rules::cachedir::CACHEDIR_TAG;
+use crate::config::Config;
+use crate::scanner::disk_usage::{DiskSpace, disk_space};
+use crate::scanner::{CACHE_LANGUAGE, ScanProgress, ScanRoot, SizeCache, scan};
+use std::sync::Mutex;
+use std::path::Path;
+use std::fs;
+use tempfile::TempDir;
+
+fn roots(dir: &Path, max_depth: usize) -> Vec<ScanRoot> {
+    vec![root(dir, max_depth)]
+}
+
+fn root(dir: &Path, max_depth: usize) -> ScanRoot {
+    ScanRoot { path: dir.display().to_string(), max_depth, follow_symlinks: false, one_file_system: false }
+}
+
+#[test]
+fn test_scan_finds_artifacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    fs::write(project.join("target").join("blob"), vec![0u8; 100]).unwrap();
+    fs::write(project.join("target").join(".rustc_info.json"), "").unwrap();
+    fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+
+    let logs = Mutex::new(vec![]);
+    let progress = ScanProgress::new();
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &progress, |line| logs.lock().unwrap().push(line));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].language, "Rust");
+    assert_eq!(found[0].size, 100);
+    assert!(found[0].verified);
+    assert_eq!(found[0].project_path, project.display().to_string());
+    assert!(logs.into_inner().unwrap().last().unwrap().contains("Found 1 artifacts"));
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.artifacts_found, 1);
+    assert_eq!(snapshot.bytes_counted, 100);
+    assert_eq!(snapshot.dirs_pending, 0);
+    assert!(snapshot.dirs_visited >= 3);
+}
+
+#[test]
+fn test_scan_skips_excluded_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("proj").join("node_modules")).unwrap();
+    fs::write(temp_dir.path().join("proj").join("package.json"), "{}").unwrap();
+    let excluded = vec![temp_dir.path().join("proj").display().to_string()];
+    let found = scan(&roots(temp_dir.path(), 3), &excluded, &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert!(found.is_empty());
+}
+
+#[test]
+fn test_scan_requires_project_marker() {
+    let temp_dir = TempDir::new().unwrap();
+    // A "build" directory without a project marker is left alone
+    fs::create_dir_all(temp_dir.path().join("docs").join("build")).unwrap();
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert!(found.is_empty());
+}
+
+#[test]
+fn test_scan_marks_unverified_artifacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("lib");
+    fs::create_dir_all(project.join("build").join("docs")).unwrap();
+    fs::create_dir_all(project.join("cmake-build-debug")).unwrap();
+    fs::write(project.join("CMakeLists.txt"), "").unwrap();
+    fs::write(project.join("cmake-build-debug").join("CMakeCache.txt"), "").unwrap();
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    let verified = |name: &str| {
+        found.iter().find(|a| a.path == project.join(name).display().to_string()).map(|a| a.verified)
+    };
+    // A hand-written build/ folder has no CMakeCache.txt
+    assert_eq!(verified("build"), Some(false));
+    assert_eq!(verified("cmake-build-debug"), Some(true));
+}
+
+#[test]
+fn test_scan_finds_cachedir_tagged_directories() {
+    let temp_dir = TempDir::new().unwrap();
+    let tagged = temp_dir.path().join("home").join("pip-cache");
+    fs::create_dir_all(tagged.join("nested")).unwrap();
+    fs::write(
+        tagged.join(CACHEDIR_TAG),
+        "Signature: 8a477f597d28d172789f06886806bc55\n# This file is a cache directory tag created by pip.\n",
+    )
+    .unwrap();
+    fs::write(tagged.join("nested").join(CACHEDIR_TAG), "Signature: 8a477f597d28d172789f06886806bc55\n").unwrap();
+    // A wrong signature is ignored
+    let bogus = temp_dir.path().join("home").join("notes");
+    fs::create_dir_all(&bogus).unwrap();
+    fs::write(bogus.join(CACHEDIR_TAG), "Signature: not-a-cache\n").unwrap();
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].path, tagged.display().to_string());
+    assert_eq!(found[0].language, CACHE_LANGUAGE);
+    assert!(found[0].verified);
+    assert_eq!(found[0].description.as_deref(), Some("This file is a cache directory tag created by pip."));
+}
+
+#[test]
+fn test_cachedir_tag_verifies_rule_artifacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("crate");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    fs::write(
+        project.join("target").join(CACHEDIR_TAG),
+        "Signature: 8a477f597d28d172789f06886806bc55\n# This file is a cache directory tag created by cargo.\n",
+    )
+    .unwrap();
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].language, "Rust");
+    assert!(found[0].verified);
+    assert_eq!(found[0].description.as_deref(), Some("This file is a cache directory tag created by cargo."));
+}
+
+#[test]
+fn test_scan_does_not_descend_into_artifacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let app = temp_dir.path().join("app");
+    let package = app.join("node_modules").join("left-pad");
+    fs::create_dir_all(package.join("dist")).unwrap();
+    fs::write(app.join("package.json"), "{}").unwrap();
+    fs::write(package.join("package.json"), "{}").unwrap();
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    let paths: Vec<&str> = found.iter().map(|a| a.path.as_str()).collect();
+    assert_eq!(paths, vec![app.join("node_modules").display().to_string()]);
+}
+
+#[test]
+fn test_scan_uses_user_rules() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("site");
+    fs::create_dir_all(project.join("_site")).unwrap();
+    fs::write(project.join("_config.yml"), "").unwrap();
+    let rules = RuleSet::new(&[Rule {
+        ecosystem: "Jekyll".to_string(),
+        markers: vec!["_config.yml".to_string()],
+        artifacts: vec!["_site".to_string()],
+        fingerprints: vec!["_site/feed.xml".to_string()],
+    }]);
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &rules, &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].language, "Jekyll");
+    assert!(!found[0].verified);
+    assert_eq!(found[0].path, project.join("_site").display().to_string());
+}
+
+#[test]
+fn test_scan_depth_is_configurable() {
+    let temp_dir = TempDir::new().unwrap();
+    let crate_dir = temp_dir.path().join("org").join("repo").join("crates").join("foo");
+    fs::create_dir_all(crate_dir.join("target")).unwrap();
+    fs::write(crate_dir.join("Cargo.toml"), "").unwrap();
+
+    // foo sits four levels below the scan path
+    assert!(scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {}).is_empty());
+    let found = scan(&roots(temp_dir.path(), 4), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, crate_dir.join("target").display().to_string());
+}
+
+#[test]
+fn test_scan_roots_use_per_path_depth() {
+    let mut config = Config { max_depth: 5, ..Config::default() };
+    config.scan_depths.insert("/deep".to_string(), 9);
+    let roots = ScanRoot::from_config(&["/deep".to_string(), "/other".to_string()], &config);
+    assert_eq!(roots[0].max_depth, 9);
+    assert_eq!(roots[1].max_depth, 5);
+}
+
+#[test]
+fn test_scan_skips_vcs_dirs() {
+    let temp_dir = TempDir::new().unwrap();
+    let vendored = temp_dir.path().join(".git").join("modules").join("dep");
+    fs::create_dir_all(vendored.join("target")).unwrap();
+    fs::write(vendored.join("Cargo.toml"), "").unwrap();
+
+    assert!(scan(&roots(temp_dir.path(), 6), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {}).is_empty());
+}
+
+#[test]
+fn test_scan_walks_roots_in_parallel_and_sorts() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut roots = vec![];
+    for name in ["b", "a", "c"] {
+        let project = temp_dir.path().join(name).join("proj");
+        fs::create_dir_all(project.join("target")).unwrap();
+        fs::write(project.join("Cargo.toml"), "").unwrap();
+        roots.push(root(&temp_dir.path().join(name), 2));
+    }
+    // Overlapping roots do not duplicate artifacts
+    roots.push(root(temp_dir.path(), 3));
+
+    let found = scan(&roots, &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    let paths: Vec<String> = found.iter().map(|a| a.path.clone()).collect();
+    let expected: Vec<String> =
+        ["a", "b", "c"].iter().map(|n| temp_dir.path().join(n).join("proj").join("target").display().to_string()).collect();
+    assert_eq!(paths, expected);
+}
+
+#[test]
+fn test_cancelled_scan_stops_and_reports() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+
+    let progress = ScanProgress::new();
+    progress.cancel();
+    let logs = Mutex::new(vec![]);
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &progress, |line| logs.lock().unwrap().push(line));
+    assert!(found.is_empty());
+    assert!(progress.snapshot().cancelled);
+    assert!(logs.into_inner().unwrap().last().unwrap().starts_with("Scan cancelled"));
+}
+
+#[test]
+fn test_progress_eta() {
+    let progress = ScanProgress::new();
+    assert_eq!(progress.snapshot().eta, None);
+    progress.queue_dirs(4);
+    progress.visit_dir();
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.dirs_pending, 3);
+    assert_eq!(snapshot.eta, Some(snapshot.elapsed.mul_f64(3.0)));
+}
+
+#[tokio::test]
+async fn test_rescan_uses_size_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    let deps = project.join("target").join("debug").join("deps");
+    let build = project.join("target").join("debug").join("build");
+    fs::create_dir_all(&deps).unwrap();
+    fs::create_dir_all(&build).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    fs::write(deps.join("lib.rlib"), vec![0u8; 300]).unwrap();
+    fs::write(build.join("out"), vec![0u8; 50]).unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let rules = RuleSet::new(&[]);
+
+    let rescan = |expected_size: u64| {
+        let store = store.clone();
+        let roots = roots(temp_dir.path(), 3);
+        let rules = &rules;
+        async move {
+            let cache = SizeCache::load(store.as_ref()).await;
+            let progress = ScanProgress::new();
+            let found = scan(&roots, &[], rules, &cache, &progress, |_| {});
+            cache.save(store.as_ref()).await.unwrap();
+            assert_eq!(found[0].size, expected_size);
+            progress.snapshot()
+        }
+    };
+
+    let first = rescan(350).await;
+    assert_eq!(first.sized_dirs, 4);
+    assert_eq!(first.cached_dirs, 0);
+
+    let second = rescan(350).await;
+    assert_eq!(second.cached_dirs, 4);
+    assert_eq!(second.cached_bytes, 350);
+
+    // Only the changed directory is listed again
+    fs::write(deps.join("new.rlib"), vec![0u8; 25]).unwrap();
+    let third = rescan(375).await;
+    assert_eq!(third.cached_dirs, 3);
+    assert_eq!(third.cached_bytes, 50);
+
+    // A file rewritten in place leaves its directory's mtime alone but is still counted anew
+    fs::write(deps.join("lib.rlib"), vec![0u8; 500]).unwrap();
+    let fourth = rescan(575).await;
+    assert_eq!(fourth.cached_dirs, 4);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_scan_records_usage_signals() {
+    use std::time::{Duration, SystemTime};
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    let deps = project.join("target").join("debug").join("deps");
+    fs::create_dir_all(&deps).unwrap();
+    fs::create_dir_all(project.join("src")).unwrap();
+    let day = Duration::from_secs(86_400);
+    let set_age = |path: &Path, days: u32| {
+        fs::File::open(path).unwrap().set_modified(SystemTime::now() - day * days).unwrap();
+    };
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    fs::write(project.join("src").join("main.rs"), "").unwrap();
+    fs::write(deps.join("old.rlib"), vec![0u8; 10]).unwrap();
+    fs::write(deps.join("new.rlib"), vec![0u8; 10]).unwrap();
+    set_age(&project.join("Cargo.toml"), 30);
+    set_age(&project.join("src").join("main.rs"), 20);
+    set_age(&deps.join("old.rlib"), 50);
+    set_age(&deps.join("new.rlib"), 40);
+    // Directory times count too, as files come and go in them
+    for dir in [project.join("target"), project.join("target").join("debug"), deps.clone()] {
+        set_age(&dir, 45);
+    }
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let rules = RuleSet::new(&[]);
+
+    let days_ago = |time: Option<chrono::DateTime<chrono::Utc>>| (chrono::Utc::now() - time.unwrap()).num_days();
+    for _ in 0..2 {
+        // The second pass takes the newest times from the size cache
+        let cache = SizeCache::load(store.as_ref()).await;
+        let found = scan(&roots(temp_dir.path(), 3), &[], &rules, &cache, &ScanProgress::new(), |_| {});
+        cache.save(store.as_ref()).await.unwrap();
+        let signals = found[0].signals;
+        assert_eq!(days_ago(signals.content_modified), 40);
+        // Files in the artifact are not source
+        assert_eq!(days_ago(signals.source_modified), 20);
+        assert_eq!(signals.last_commit, None);
+    }
+
+    // A build rewriting a file in place leaves every directory time alone
+    fs::write(deps.join("old.rlib"), vec![1u8; 10]).unwrap();
+    let cache = SizeCache::load(store.as_ref()).await;
+    let progress = ScanProgress::new();
+    let found = scan(&roots(temp_dir.path(), 3), &[], &rules, &cache, &progress, |_| {});
+    assert_eq!(progress.snapshot().cached_dirs, 3);
+    assert_eq!(days_ago(found[0].signals.content_modified), 0);
+}
+
+#[test]
+fn test_source_times_follow_scan_policies() {
+    use std::time::{Duration, SystemTime};
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    let nested = project.join("tools").join("web");
+    for dir in [project.join("target"), nested.join("node_modules"), project.join("vendor"), project.join("a").join("b").join("c")] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let set_age = |path: &Path, days: u32| {
+        let file = fs::OpenOptions::new().create(true).truncate(true).write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(86_400) * days).unwrap();
+    };
+    set_age(&project.join("Cargo.toml"), 30);
+    set_age(&nested.join("package.json"), 10);
+    // Fresh, but excluded or deeper than the scan goes
+    set_age(&project.join("vendor").join("lib.rs"), 0);
+    set_age(&project.join("a").join("b").join("c").join("deep.rs"), 0);
+
+    let excluded = vec![project.join("vendor").display().to_string()];
+    let found = scan(&roots(temp_dir.path(), 3), &excluded, &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    let days_ago = |artifact: &crate::scanner::FoundArtifact| (chrono::Utc::now() - artifact.signals.source_modified.unwrap()).num_days();
+    assert_eq!(found.len(), 2);
+    // The nested project's files count for the project around it too
+    assert!(found.iter().all(|artifact| days_ago(artifact) == 10), "{:?}", found);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hardlinks_count_once_on_disk() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = temp_dir.path().join("store");
+    fs::create_dir_all(&store).unwrap();
+    fs::write(store.join("blob"), vec![1u8; 64 * 1024]).unwrap();
+    for name in ["a", "b"] {
+        let project = temp_dir.path().join(name);
+        fs::create_dir_all(project.join("node_modules")).unwrap();
+        fs::write(project.join("package.json"), "{}").unwrap();
+        fs::hard_link(store.join("blob"), project.join("node_modules").join("blob")).unwrap();
+    }
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found.len(), 2);
+    // Both report the full length, but the shared blocks are only counted once
+    assert!(found.iter().all(|a| a.size == 64 * 1024));
+    let on_disk: Vec<u64> = found.iter().map(|a| a.disk_size).collect();
+    assert!(on_disk.contains(&0), "{:?}", on_disk);
+    assert!(on_disk.iter().sum::<u64>() >= 64 * 1024, "{:?}", on_disk);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sparse_files_use_allocated_blocks() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    let file = fs::File::create(project.join("target").join("sparse")).unwrap();
+    file.set_len(64 * 1024 * 1024).unwrap();
+
+    let found = scan(&roots(temp_dir.path(), 3), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found[0].size, 64 * 1024 * 1024);
+    assert!(found[0].disk_size < found[0].size);
+}
+
+#[tokio::test]
+async fn test_purge_by_policy_removes_from_disk_and_store() {
+    let temp_dir = TempDir::new().unwrap();
+    let target = temp_dir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let target_path = target.display().to_string();
+    store.record_observation(&temp_dir.path().display().to_string(), "Rust", &target_path, 0.into(), true, None).await.unwrap();
+
+    let guard = PathGuard::new(&[temp_dir.path().display().to_string()]);
+
+    let summary = purge_by_policy(store.as_ref(), &RetentionPolicy::new(1), &guard, false).await.unwrap();
+    assert!(summary.removed.is_empty());
+    assert!(target.exists());
+
+    let summary = purge_by_policy(store.as_ref(), &RetentionPolicy::new(0), &guard, false).await.unwrap();
+    assert_eq!(summary.removed, vec![target_path]);
+    assert_eq!(summary.total.removed_dirs, 1);
+    assert!(!target.exists());
+    assert!(store.current_artifacts().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_purge_refuses_paths_outside_scan_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    let target = outside.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let target_path = target.display().to_string();
+    store.record_observation(&outside.path().display().to_string(), "Rust", &target_path, 0.into(), true, None).await.unwrap();
+    let guard = PathGuard::new(&[temp_dir.path().display().to_string()]);
+
+    let summary = purge_by_policy(store.as_ref(), &RetentionPolicy::new(0), &guard, false).await.unwrap();
+    assert!(summary.removed.is_empty());
+    assert!(matches!(&summary.refused[..], [Refusal::OutsideScanPaths { path, .. }] if path == &target_path));
+    assert!(target.exists());
+    assert_eq!(store.current_artifacts().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_purge_writes_the_deletion_log() {
+    let temp_dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let mut paths = vec![];
+    for (dir, name) in [(temp_dir.path(), "kept"), (temp_dir.path(), "moved"), (outside.path(), "stray")] {
+        let target = dir.join(name).join("target");
+        fs::create_dir_all(&target).unwrap();
+        let size = crate::db::store::DiskUsage { apparent_bytes: 300, disk_bytes: 400 };
+        store.record_observation(&dir.join(name).display().to_string(), "Rust", &target.display().to_string(), size, true, None).await.unwrap();
+        paths.push(target.display().to_string());
+    }
+    let guard = PathGuard::new(&[temp_dir.path().display().to_string()]);
+
+    purge_by_policy(store.as_ref(), &RetentionPolicy::new(0), &guard, false).await.unwrap();
+    let deletions = store.deletions(None, 10).await.unwrap();
+    assert_eq!(deletions.len(), 3);
+    let removed = deletions.iter().find(|d| d.path == paths[0]).unwrap();
+    assert_eq!((removed.trigger.as_str(), removed.outcome.as_str()), (TRIGGER_RETENTION, "removed"));
+    assert_eq!((removed.size_bytes, removed.disk_bytes, removed.detail.as_deref()), (300, Some(400), None));
+    assert!(!removed.user.is_empty());
+    let refused = deletions.iter().find(|d| d.path == paths[2]).unwrap();
+    assert_eq!(refused.outcome, "refused");
+    assert!(refused.detail.as_deref().unwrap().starts_with("refusing to delete"));
+
+    // Quarantining and the later purge are both logged
+    fs::create_dir_all(&paths[1]).unwrap();
+    store.record_observation(&temp_dir.path().join("moved").display().to_string(), "Rust", &paths[1], 300.into(), true, None).await.unwrap();
+    purge_by_policy(store.as_ref(), &RetentionPolicy::new(0), &guard, true).await.unwrap();
+    quarantine::purge_expired(store.as_ref(), 0, &guard).await.unwrap();
+    let latest: Vec<(String, String)> =
+        store.deletions(Some(&paths[1]), 2).await.unwrap().into_iter().map(|d| (d.trigger, d.outcome)).collect();
+    assert_eq!(
+        latest,
+        vec![(TRIGGER_QUARANTINE_EXPIRY.to_string(), "removed".to_string()), (TRIGGER_RETENTION.to_string(), "quarantined".to_string())]
+    );
+}
+
+#[test]
+fn test_removal_outcomes() {
+    let denied = || RemovalFailure { path: "/p/target/x".into(), error: std::io::Error::from(std::io::ErrorKind::PermissionDenied) };
+    assert_eq!(Outcome::of_removal(&RemovalReport::default()), Outcome::Removed);
+    let failed = Outcome::of_removal(&RemovalReport { failures: vec![denied(), denied()], ..Default::default() });
+    assert_eq!(failed.as_str(), "failed");
+    assert!(failed.detail().unwrap().starts_with("/p/target/x: permission denied"));
+    assert!(failed.detail().unwrap().ends_with("(and 1 more)"));
+    let partial = Outcome::of_removal(&RemovalReport { removed_files: 3, failures: vec![denied()], ..Default::default() });
+    assert_eq!(partial.as_str(), "partial");
+    assert!(!partial.is_gone());
+    assert_eq!(Outcome::AlreadyGone.as_str(), "removed");
+}
+
+#[test]
+fn test_guard_refuses_scan_roots_and_missing_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let scan_path = temp_dir.path().display().to_string();
+    let guard = PathGuard::new(std::slice::from_ref(&scan_path));
+    fs::create_dir_all(temp_dir.path().join("proj/target")).unwrap();
+
+    assert!(guard.check(&temp_dir.path().join("proj/target").display().to_string()).is_ok());
+    assert!(matches!(guard.check(&scan_path), Err(Refusal::ScanRoot { .. })));
+    assert!(matches!(guard.check(&format!("{}/proj/..", scan_path)), Err(Refusal::ScanRoot { .. })));
+    assert!(matches!(guard.check(&format!("{}/missing", scan_path)), Err(Refusal::Unresolvable { .. })));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_guard_refuses_symlinks_escaping_scan_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("target")).unwrap();
+    let guard = PathGuard::new(&[temp_dir.path().display().to_string()]);
+
+    let refusal = guard.check(&temp_dir.path().join("target").display().to_string()).unwrap_err();
+    assert!(matches!(refusal, Refusal::OutsideScanPaths { .. }));
+    assert!(refusal.to_string().starts_with("refusing to delete"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_scan_follows_symlinks_only_when_enabled() {
+    let temp_dir = TempDir::new().unwrap();
+    let elsewhere = TempDir::new().unwrap();
+    let project = elsewhere.path().join("proj");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    std::os::unix::fs::symlink(elsewhere.path(), temp_dir.path().join("linked")).unwrap();
+    let logs = Mutex::new(vec![]);
+    let log = |line: String| logs.lock().unwrap().push(line);
+
+    let found = scan(&roots(temp_dir.path(), 4), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), log);
+    assert!(found.is_empty());
+    assert!(logs.lock().unwrap().iter().any(|line| line.starts_with("Skipped 1 symlinked")));
+
+    let mut following = root(temp_dir.path(), 4);
+    following.follow_symlinks = true;
+    let found = scan(&[following], &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert_eq!(found.len(), 1);
+}
+
+#[test]
+fn test_remove_tree_counts_what_it_removed() {
+    let temp_dir = TempDir::new().unwrap();
+    let target = temp_dir.path().join("target");
+    fs::create_dir_all(target.join("debug/deps")).unwrap();
+    fs::write(target.join("debug/deps/a.rlib"), vec![0u8; 300]).unwrap();
+    fs::write(target.join("debug/b"), vec![0u8; 200]).unwrap();
+
+    let report = remove_tree(&target);
+    assert!(report.is_complete());
+    assert_eq!((report.removed_files, report.removed_dirs, report.removed_bytes), (2, 3, 500));
+    assert!(!target.exists());
+    assert!(report.summary().starts_with("Removed 2 files and 3 directories (500 B)."));
+
+    // Removing what is already gone is not a failure
+    assert!(remove_tree(&target).is_complete());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_remove_tree_does_not_follow_symlinks() {
+    let temp_dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    fs::write(outside.path().join("keep"), "").unwrap();
+    let target = temp_dir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    std::os::unix::fs::symlink(outside.path(), target.join("link")).unwrap();
+
+    let report = remove_tree(&target);
+    assert!(report.is_complete());
+    assert!(!target.exists());
+    assert!(outside.path().join("keep").exists());
+}
+
+#[test]
+fn test_removal_report_lists_leftovers() {
+    let mut report = RemovalReport { removed_files: 4, ..Default::default() };
+    report.merge(RemovalReport {
+        removed_files: 1,
+        failures: vec![
+            RemovalFailure { path: "/srv/a/target/x".into(), error: std::io::ErrorKind::PermissionDenied.into() },
+            RemovalFailure { path: "/srv/a/target/y".into(), error: std::io::ErrorKind::Other.into() },
+        ],
+        ..Default::default()
+    });
+
+    assert!(!report.is_complete());
+    assert_eq!(report.removed_files, 5);
+    assert_eq!(report.permission_denied(), vec![std::path::PathBuf::from("/srv/a/target/x")]);
+    assert!(report.needs_password());
+    let summary = report.summary();
+    assert!(summary.contains("2 paths left:"));
+    assert!(summary.contains("/srv/a/target/y"));
+}
+
+#[tokio::test]
+async fn test_quarantine_and_restore() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    let target = project.join("target");
+    fs::create_dir_all(target.join("debug")).unwrap();
+    fs::write(target.join("debug/app"), "bin").unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let target_path = target.display().to_string();
+    store.record_observation(&project.display().to_string(), "Rust", &target_path, 3.into(), true, None).await.unwrap();
+    let guard = PathGuard::new(&[temp_dir.path().display().to_string()]);
+
+    let entry = quarantine::quarantine(store.as_ref(), &guard, &target_path).await.unwrap();
+    assert!(!target.exists());
+    let holding = Path::new(&entry.holding_path);
+    assert!(holding.join("debug/app").exists());
+    assert_eq!(holding.parent().unwrap(), fs::canonicalize(temp_dir.path()).unwrap().join(QUARANTINE_DIR));
+    assert!(store.current_artifacts().await.unwrap().is_empty());
+
+    // The holding area is never scanned
+    let found = scan(&roots(temp_dir.path(), 4), &[], &RuleSet::new(&[]), &SizeCache::default(), &ScanProgress::new(), |_| {});
+    assert!(found.is_empty());
+
+    // Restoring refuses to overwrite what took its place
+    fs::create_dir_all(&target).unwrap();
+    assert!(matches!(quarantine::restore(store.as_ref(), &entry).await, Err(QuarantineError::Occupied { .. })));
+    fs::remove_dir(&target).unwrap();
+
+    quarantine::restore(store.as_ref(), &entry).await.unwrap();
+    assert!(target.join("debug/app").exists());
+    assert!(store.quarantined().await.unwrap().is_empty());
+    assert_eq!(store.current_artifacts().await.unwrap()[0].path, target_path);
+}
+
+#[tokio::test]
+async fn test_purge_quarantines_then_purges_expired() {
+    let temp_dir = TempDir::new().unwrap();
+    let target = temp_dir.path().join("proj/target");
+    fs::create_dir_all(&target).unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let target_path = target.display().to_string();
+    store.record_observation(&temp_dir.path().join("proj").display().to_string(), "Rust", &target_path, 0.into(), true, None).await.unwrap();
+    let guard = PathGuard::new(&[temp_dir.path().display().to_string()]);
+
+    let summary = purge_by_policy(store.as_ref(), &RetentionPolicy::new(0), &guard, true).await.unwrap();
+    assert_eq!(summary.quarantined, vec![target_path.clone()]);
+    assert!(summary.removed.is_empty());
+    let holding = store.quarantined().await.unwrap()[0].holding_path.clone();
+    assert!(Path::new(&holding).exists());
+
+    let expired = quarantine::purge_expired(store.as_ref(), 1, &guard).await.unwrap();
+    assert!(expired.removed.is_empty());
+    let expired = quarantine::purge_expired(store.as_ref(), 0, &guard).await.unwrap();
+    assert_eq!(expired.removed, vec![target_path]);
+    assert!(!Path::new(&holding).exists());
+    assert!(store.quarantined().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_deletion_plan_names_rules_and_totals() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("proj");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::create_dir_all(project.join(".cache")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    fs::write(project.join(".cache").join(CACHEDIR_TAG), "Signature: 8a477f597d28d172789f06886806bc55\n").unwrap();
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let project_path = project.display().to_string();
+    let target = project.join("target").display().to_string();
+    let cache = project.join(".cache").display().to_string();
+    let size = |apparent_bytes, disk_bytes| crate::db::store::DiskUsage { apparent_bytes, disk_bytes };
+    store.record_observation(&project_path, "Rust", &target, size(100, 400), true, None).await.unwrap();
+    store.record_observation(&project_path, "Rust", &cache, size(300, 300), true, None).await.unwrap();
+    store.record_observation("/gone", "Go", "/gone/vendor", size(5, 5), false, None).await.unwrap();
+    let rules = RuleSet::new(&[]);
+
+    let mut plan = DeletionPlan::clear_all(store.as_ref(), &rules).await.unwrap();
+    let rows: Vec<(&str, &str)> = plan.artifacts.iter().map(|a| (a.path.as_str(), a.rule.as_str())).collect();
+    // Biggest on disk first
+    assert_eq!(rows, vec![(target.as_str(), "Rust: target"), (cache.as_str(), "CACHEDIR.TAG"), ("/gone/vendor", "Go (as recorded)")]);
+    // Unverified directories are offered but not selected
+    assert_eq!(plan.selected_bytes(), (400, 700));
+    assert_eq!(plan.unverified_kept(), 1);
+    assert_eq!(plan.artifacts[0].age_days, 0);
+
+    plan.toggle(0);
+    assert_eq!(plan.selected().map(|a| a.path.as_str()).collect::<Vec<_>>(), vec![cache.as_str()]);
+    assert_eq!(plan.selected_bytes(), (300, 300));
+    plan.toggle_all();
+    assert_eq!(plan.selected().count(), 2);
+    plan.toggle(2);
+    assert_eq!(plan.selected().count(), 3);
+    assert_eq!(plan.unverified_kept(), 0);
+    plan.toggle_all();
+    assert_eq!(plan.selected().count(), 0);
+
+    // Retention plans only hold verified artifacts past the retention period
+    assert!(DeletionPlan::retention(store.as_ref(), &RetentionPolicy::new(1), &rules).await.unwrap().is_empty());
+    assert_eq!(DeletionPlan::retention(store.as_ref(), &RetentionPolicy::new(0), &rules).await.unwrap().artifacts.len(), 2);
+}
+
+fn policy_record(path: &str, idle_days: i64, disk_bytes: u64, verified: bool) -> ArtifactRecord {
+    let seen = chrono::Utc::now() - chrono::Duration::days(idle_days);
+    ArtifactRecord {
+        path: path.to_string(),
+        project_path: Path::new(path).parent().unwrap().display().to_string(),
+        language: "Rust".to_string(),
+        size_bytes: disk_bytes,
+        disk_bytes: Some(disk_bytes),
+        first_seen: seen,
+        last_seen: seen,
+        verified,
+        description: None,
+        signals: UsageSignals::default(),
+        pin: None,
+    }
+}
+
+#[test]
+fn test_policy_limits_pick_least_recently_used() {
+    let records = vec![
+        policy_record("/p/a/t1", 40, 100, true),
+        policy_record("/p/a/t2", 5, 100, true),
+        policy_record("/p/a/t3", 3, 100, true),
+        policy_record("/p/a/t4", 1, 100, true),
+        policy_record("/p/b/t1", 10, 500, false),
+        policy_record("/p/b/t2", 8, 300, true),
+    ];
+    let now = chrono::Utc::now();
+    let mut policy = RetentionPolicy::new(30);
+    policy.max_per_project = Some(2);
+    policy.max_total_bytes = Some(600);
+    let decisions = policy.evaluate(&records, now, |_| None);
+    // Age goes first, then the oldest beyond two per project, then the oldest
+    // verified ones until the rest, unverified included, fits in 600 bytes
+    let picked: Vec<_> = decisions.iter().map(|d| (d.path.as_str(), d.limit)).collect();
+    assert_eq!(
+        picked,
+        vec![
+            ("/p/a/t1", Limit::Age),
+            ("/p/b/t2", Limit::TotalSize),
+            ("/p/a/t2", Limit::PerProject),
+            ("/p/a/t3", Limit::TotalSize),
+        ]
+    );
+    assert!(decisions[1].reason.starts_with("artifacts over 600 B in total; unused for 8 days (first seen by ratifact, "));
+    assert!(decisions[2].reason.starts_with("over 2 artifacts in its project; unused for 5 days"));
+
+    // Only the filesystem short of space gives up artifacts, just enough to recover
+    let full = DiskSpace { device: 1, available_bytes: 50, total_bytes: 1000 };
+    let roomy = DiskSpace { device: 2, available_bytes: 500, total_bytes: 1000 };
+    let mut policy = RetentionPolicy::new(365);
+    policy.min_free_percent = Some(10);
+    let decisions = policy.evaluate(&records, now, |path| Some(if path.starts_with("/p/a") { full } else { roomy }));
+    assert_eq!(decisions.iter().map(|d| d.path.as_str()).collect::<Vec<_>>(), vec!["/p/a/t1"]);
+    assert_eq!(decisions[0].limit, Limit::FreeSpace);
+    assert!(decisions[0].reason.starts_with("5% free on its filesystem, below 10%; unused for 40 days"));
+
+    #[cfg(unix)]
+    {
+        let temp_dir = TempDir::new().unwrap();
+        let space = disk_space(temp_dir.path()).unwrap();
+        assert!(space.total_bytes > 0 && space.available_bytes <= space.total_bytes);
+    }
+}
+
+#[tokio::test]
+async fn test_daemon_pass_waits_for_the_cleanup_lock() {
+    use crate::cleanup::lock::{CleanupLock, LockError};
+    use crate::cli::daemon::{STATUS_COMPLETED, STATUS_SKIPPED, pass};
+    use crate::tracking::logger::BuildLogger;
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("app");
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    let logger = BuildLogger { store: open_store("sqlite::memory:").await.unwrap() };
+    let config = Config { scan_paths: vec![temp_dir.path().display().to_string()], ..Config::default() };
+
+    // The TUI is deleting: the pass still rescans but leaves cleanup alone
+    let lock = CleanupLock::acquire(&logger.store, "tui").await.unwrap();
+    assert!(matches!(CleanupLock::acquire(&logger.store, "clean").await, Err(LockError::Busy(_))));
+    let run = pass(&logger, &config).await;
+    assert_eq!((run.status.as_str(), run.artifacts), (STATUS_SKIPPED, 1));
+    assert!(run.detail.unwrap().starts_with("another cleanup is running: tui (pid "));
+    lock.release(logger.store.as_ref()).await.unwrap();
+
+    let run = pass(&logger, &config).await;
+    assert_eq!((run.status.as_str(), run.removed, run.detail), (STATUS_COMPLETED, 0, None));
+    // The pass released the lock again
+    CleanupLock::acquire(&logger.store, "clean").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pinned_artifacts_are_never_picked() {
+    let now = chrono::Utc::now();
+    let mut pinned = policy_record("/p/a/t1", 40, 100, true);
+    pinned.pin = Some(Pin::new(pinned.path.clone(), Some("release".to_string()), None, now));
+    let mut lapsed = policy_record("/p/a/t2", 40, 100, true);
+    lapsed.pin = Some(Pin::new(lapsed.path.clone(), None, Some(1), now - chrono::Duration::days(2)));
+    let records = vec![pinned, lapsed, policy_record("/p/b/t1", 5, 100, true)];
+    assert!(records[0].is_pinned(now));
+    assert!(!records[1].is_pinned(now));
+
+    // Pinned artifacts still count toward the limits, so others go in their place
+    let mut policy = RetentionPolicy::new(30);
+    policy.max_total_bytes = Some(150);
+    let decisions = policy.evaluate(&records, now, |_| None);
+    assert_eq!(decisions.iter().map(|d| d.path.as_str()).collect::<Vec<_>>(), vec!["/p/a/t2", "/p/b/t1"]);
+
+    // Clear-all and retention plans leave them out and say so
+    let store = open_store("sqlite::memory:").await.unwrap();
+    let size = crate::db::store::DiskUsage { apparent_bytes: 100, disk_bytes: 100 };
+    store.record_observation("/p/a", "Rust", "/p/a/target", size, true, None).await.unwrap();
+    store.record_observation("/p/b", "Rust", "/p/b/target", size, true, None).await.unwrap();
+    store.pin(&Pin::new("/p/a/target".to_string(), None, None, now)).await.unwrap();
+    let rules = RuleSet::new(&[]);
+    let plan = DeletionPlan::clear_all(store.as_ref(), &rules).await.unwrap();
+    assert_eq!(plan.artifacts.iter().map(|a| a.path.as_str()).collect::<Vec<_>>(), vec!["/p/b/target"]);
+    assert_eq!(plan.pinned, 1);
+    assert!(crate::ui::popup::plan_summary(&plan).ends_with(" · 1 pinned kept"));
+    let plan = DeletionPlan::retention(store.as_ref(), &RetentionPolicy::new(0), &rules).await.unwrap();
+    assert_eq!((plan.artifacts.len(), plan.pinned), (1, 1));
+}
+
+#[test]
+fn test_policy_blocks_override_retention() {
+    let block = |name: &str, paths: &[&str], languages: &[&str], rules: &[&str], retention_days: Option<u32>| PolicyBlock {
+        name: name.to_string(),
+        paths: paths.iter().map(|p| p.to_string()).collect(),
+        languages: languages.iter().map(|l| l.to_string()).collect(),
+        rules: rules.iter().map(|r| r.to_string()).collect(),
+        retention_days,
+        keep: retention_days.is_none(),
+    };
+    let blocks = vec![
+        block("scratch", &["/home/u/scratch/**"], &[], &["node_modules"], Some(3)),
+        block("product", &["/work/product/**"], &["rust"], &[], Some(60)),
+        block("precious", &["/keep/**"], &[], &[], None),
+    ];
+    let mut node_modules = policy_record("/home/u/scratch/app/node_modules", 5, 100, true);
+    node_modules.language = "JavaScript".to_string();
+    let records = vec![
+        node_modules,
+        policy_record("/home/u/scratch/app/target", 5, 100, true),
+        policy_record("/work/product/target", 45, 100, true),
+        policy_record("/work/other/target", 45, 100, true),
+        policy_record("/keep/tool/target", 400, 100, true),
+    ];
+    let policy = RetentionPolicy::new(30).with_blocks(&blocks);
+    assert_eq!(policy.describe_for(&records[0]), "scratch: 3 days");
+    assert_eq!(policy.describe_for(&records[1]), "default: 30 days");
+    assert_eq!(policy.describe_for(&records[2]), "product: 60 days");
+    assert_eq!(policy.describe_for(&records[4]), "precious: never removed");
+
+    let now = chrono::Utc::now();
+    let decisions = policy.evaluate(&records, now, |_| None);
+    let picked: Vec<_> = decisions.iter().map(|d| (d.path.as_str(), d.policy.as_deref())).collect();
+    assert_eq!(picked, vec![("/work/other/target", None), ("/home/u/scratch/app/node_modules", Some("scratch"))]);
+
+    // Kept artifacts are left out of the other limits too
+    let mut policy = policy;
+    policy.max_total_bytes = Some(1);
+    let decisions = policy.evaluate(&records, now, |_| None);
+    assert_eq!(decisions.len(), 4);
+    assert!(decisions.iter().all(|d| d.path != "/keep/tool/target"));
+}
+
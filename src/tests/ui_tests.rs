@@ -1,6 +1,6 @@
 // UI tests
 
-use crate::utils::detect_language_for_path;
+use crate::rules::RuleSet;
 use std::fs;
 use tempfile::TempDir;
 
@@ -10,15 +10,15 @@ fn test_detect_language() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Rust");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Rust"));
 
     // Test JS
     fs::remove_file(temp_dir.path().join("Cargo.toml")).unwrap();
     fs::write(temp_dir.path().join("package.json"), "").unwrap();
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "JavaScript");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("JavaScript"));
 
     // Test unknown
     fs::remove_file(temp_dir.path().join("package.json")).unwrap();
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Unknown");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), None);
 }
 
@@ -1,6 +1,6 @@
 // Utils tests
 
-use crate::utils::detect_language_for_path;
+use crate::rules::{ArtifactMatch, Rule, RuleSet};
 use std::fs;
 use tempfile::TempDir;
 
@@ -8,7 +8,7 @@ use tempfile::TempDir;
 fn test_detect_language_rust() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Rust");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Rust"));
 }
 
 #[test]
@@ -16,7 +16,7 @@ fn test_detect_language_js() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("package.json"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "JavaScript");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("JavaScript"));
 }
 
 #[test]
@@ -24,7 +24,7 @@ fn test_detect_language_python() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("pyproject.toml"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Python");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Python"));
 }
 
 #[test]
@@ -32,7 +32,7 @@ fn test_detect_language_go() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("go.mod"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Go");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Go"));
 }
 
 #[test]
@@ -40,7 +40,7 @@ fn test_detect_language_c_cpp() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("Makefile"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "C/C++");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("C/C++"));
 }
 
 #[test]
@@ -48,7 +48,7 @@ fn test_detect_language_java() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("pom.xml"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Java");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Java"));
 }
 
 #[test]
@@ -56,7 +56,7 @@ fn test_detect_language_php() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("composer.json"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "PHP");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("PHP"));
 }
 
 #[test]
@@ -64,7 +64,7 @@ fn test_detect_language_ruby() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("Gemfile"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Ruby");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Ruby"));
 }
 
 #[test]
@@ -72,7 +72,7 @@ fn test_detect_language_swift() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("Package.swift"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Swift");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Swift"));
 }
 
 #[test]
@@ -80,7 +80,7 @@ fn test_detect_language_kotlin() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("build.gradle.kts"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Kotlin");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Kotlin"));
 }
 
 #[test]
@@ -88,7 +88,7 @@ fn test_detect_language_scala() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("build.sbt"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Scala");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Scala"));
 }
 
 #[test]
@@ -96,7 +96,7 @@ fn test_detect_language_haskell() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("stack.yaml"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Haskell");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Haskell"));
 }
 
 #[test]
@@ -104,12 +104,96 @@ fn test_detect_language_elixir() {
     let temp_dir = TempDir::new().unwrap();
     fs::write(temp_dir.path().join("mix.exs"), "").unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Elixir");
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), Some("Elixir"));
 }
 
 #[test]
 fn test_detect_language_unknown() {
     let temp_dir = TempDir::new().unwrap();
 
-    assert_eq!(detect_language_for_path(temp_dir.path().to_str().unwrap()), "Unknown");
-}
\ No newline at end of file
+    assert_eq!(RuleSet::new(&[]).language_for(temp_dir.path()), None);
+}
+#[test]
+fn test_detect_language_glob_marker() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("App.csproj"), "").unwrap();
+    fs::create_dir_all(temp_dir.path().join("obj")).unwrap();
+    fs::write(temp_dir.path().join("obj").join("project.assets.json"), "{}").unwrap();
+    let rules = RuleSet::new(&[]);
+    assert_eq!(rules.language_for(temp_dir.path()), Some(".NET"));
+    assert_eq!(
+        rules.artifacts_in(temp_dir.path()),
+        vec![ArtifactMatch { path: temp_dir.path().join("obj"), verified: true }]
+    );
+}
+
+#[test]
+fn test_rule_validation() {
+    let rule = |markers: &[&str], artifacts: &[&str]| Rule {
+        ecosystem: "Custom".to_string(),
+        markers: markers.iter().map(|m| m.to_string()).collect(),
+        artifacts: artifacts.iter().map(|a| a.to_string()).collect(),
+        fingerprints: vec![],
+    };
+    assert!(rule(&["custom.toml"], &["out", "cache/tmp"]).validate().is_ok());
+    assert!(rule(&[], &["out"]).validate().is_err());
+    assert!(rule(&["sub/custom.toml"], &["out"]).validate().is_err());
+    assert!(rule(&["custom.toml"], &["../outside"]).validate().is_err());
+    assert!(rule(&["custom.toml"], &["/abs"]).validate().is_err());
+
+    let with_fingerprint = |fingerprint: &str| Rule {
+        fingerprints: vec![fingerprint.to_string()],
+        ..rule(&["custom.toml"], &["out"])
+    };
+    assert!(with_fingerprint("out/.stamp").validate().is_ok());
+    assert!(with_fingerprint("out/*.o").validate().is_ok());
+    // Fingerprints must live inside an artifact
+    assert!(with_fingerprint("other/.stamp").validate().is_err());
+    assert!(with_fingerprint("out").validate().is_err());
+}
+
+#[test]
+fn test_parse_age() {
+    use crate::utils::parse_age;
+    assert_eq!(parse_age("14d"), Ok(14));
+    assert_eq!(parse_age("2w"), Ok(14));
+    assert_eq!(parse_age("30"), Ok(30));
+    assert!(parse_age("xyz").is_err());
+    assert!(parse_age("d").is_err());
+    // Zero days would make everything old enough to delete
+    assert_eq!(parse_age("0d"), Err("Invalid age '0d', must be at least 1 day".to_string()));
+    assert!(parse_age("0").is_err());
+    assert!(parse_age("0w").is_err());
+}
+
+#[test]
+fn test_parse_interval() {
+    use crate::utils::parse_interval;
+    use std::time::Duration;
+    assert_eq!(parse_interval("30m"), Ok(Duration::from_secs(30 * 60)));
+    assert_eq!(parse_interval("6h"), Ok(Duration::from_secs(6 * 60 * 60)));
+    assert_eq!(parse_interval("1d"), Ok(Duration::from_secs(24 * 60 * 60)));
+    assert!(parse_interval("30").is_err());
+    assert!(parse_interval("h").is_err());
+}
+
+#[test]
+fn test_parse_size() {
+    use crate::utils::parse_size;
+    assert_eq!(parse_size("50GB"), Ok(50_000_000_000));
+    assert_eq!(parse_size("1.5 tb"), Ok(1_500_000_000_000));
+    assert_eq!(parse_size("512MiB"), Ok(512 << 20));
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert!(parse_size("GB").is_err());
+    assert!(parse_size("10 parsecs").is_err());
+}
+
+#[test]
+fn test_format_size() {
+    use crate::utils::format_size;
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1_500), "1.5 KB");
+    assert_eq!(format_size(2_000_000_000), "2.0 GB");
+    assert_eq!(crate::utils::format_size_change(1_500), "+1.5 KB");
+    assert_eq!(crate::utils::format_size_change(-512), "-512 B");
+}
@@ -1,6 +1,8 @@
 // Watcher tests
 
+use crate::tracking::activity::{ActivityTracker, Burst, record_burst};
 use crate::tracking::watcher::BuildWatcher;
+use chrono::{Duration, TimeZone, Utc};
 use std::fs;
 use tempfile::TempDir;
 
@@ -8,15 +10,96 @@ use tempfile::TempDir;
 fn test_build_watcher() {
     let temp_dir = TempDir::new().unwrap();
     let mut watcher = BuildWatcher::new(false);
+    let mut events = watcher.subscribe();
     // Watch the temp dir - may fail due to system inotify limits in tests
     // Just ensure it doesn't panic
     match watcher.watch(temp_dir.path()) {
         Ok(()) => {
             // Create a file
             fs::write(temp_dir.path().join("test.txt"), "test").unwrap();
+            // The change is reported against the watched artifact
+            for _ in 0..50 {
+                if let Ok(event) = events.try_recv() {
+                    assert_eq!(event.artifact, temp_dir.path().display().to_string());
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
         }
         Err(_) => {
             // Expected in CI environments with inotify limits - test passes
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_activity_tracker_debounces_bursts() {
+    let at = |seconds| Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 0).unwrap() + Duration::seconds(seconds);
+    let quiet = Duration::seconds(10);
+    let mut tracker = ActivityTracker::default();
+    tracker.touch("/p/a/target".to_string(), at(0));
+    tracker.touch("/p/a/target".to_string(), at(8));
+    tracker.touch("/p/b/node_modules".to_string(), at(3));
+    // Still changing within the quiet window
+    assert!(tracker.settled(at(12), quiet).is_empty());
+    tracker.touch("/p/a/target".to_string(), at(15));
+    let settled = tracker.settled(at(14), quiet);
+    assert_eq!(settled.iter().map(|b| b.artifact.as_str()).collect::<Vec<_>>(), vec!["/p/b/node_modules"]);
+    assert_eq!(
+        tracker.settled(at(25), quiet),
+        vec![Burst { artifact: "/p/a/target".to_string(), started_at: at(0), ended_at: at(15), events: 3 }]
+    );
+    assert!(tracker.settled(at(100), quiet).is_empty());
+}
+
+#[tokio::test]
+async fn test_record_burst_updates_size() {
+    use crate::db::connection::open_store;
+    use crate::db::store::{DirSizeEntry, DiskUsage};
+    use crate::tracking::logger::BuildLogger;
+    let temp_dir = TempDir::new().unwrap();
+    let target = temp_dir.path().join("proj").join("target");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("lib.rlib"), vec![0u8; 300]).unwrap();
+    let path = target.display().to_string();
+    let logger = BuildLogger { store: open_store("sqlite::memory:").await.unwrap() };
+    let project = temp_dir.path().join("proj").display().to_string();
+    logger.log_build(&project, "Rust", &path, DiskUsage::from(300), true, None).await.unwrap();
+
+    fs::write(target.join("new.rlib"), vec![0u8; 200]).unwrap();
+    let now = Utc::now();
+    let burst = Burst { artifact: path.clone(), started_at: now, ended_at: now, events: 2 };
+    let activity = record_burst(&logger, &burst, false).await.unwrap().unwrap();
+    assert_eq!((activity.size_before, activity.size_after), (Some(300), 500));
+    let record = logger.store.artifact(&path).await.unwrap().unwrap();
+    assert_eq!(record.size_bytes, 500);
+    assert_eq!(record.signals.content_modified, Some(now));
+    assert_eq!(logger.store.build_activity(10).await.unwrap().len(), 1);
+
+    // A file rewritten in place leaves its directory's mtime alone, yet the
+    // next burst still counts its new size, and other cache entries are kept
+    let other = "/p/other/target".to_string();
+    let entry = DirSizeEntry {
+        path: other.clone(),
+        inode: 7,
+        mtime_ns: 0,
+        size_bytes: 10,
+        disk_bytes: 4096,
+        linked: vec![],
+        subdirs: vec![],
+        files: vec!["lib.rlib".to_string()],
+        newest_mtime_ns: 0,
+    };
+    logger.store.replace_dir_sizes(std::slice::from_ref(&other), &[entry]).await.unwrap();
+    fs::write(target.join("lib.rlib"), vec![0u8; 100]).unwrap();
+    let activity = record_burst(&logger, &burst, false).await.unwrap().unwrap();
+    assert_eq!((activity.size_before, activity.size_after), (Some(500), 300));
+    let cached: Vec<String> = logger.store.dir_size_cache().await.unwrap().into_iter().map(|entry| entry.path).collect();
+    assert!(cached.contains(&other) && cached.contains(&path));
+
+    // A deleted artifact, or one that was never recorded, is left alone
+    fs::remove_dir_all(&target).unwrap();
+    assert!(record_burst(&logger, &burst, false).await.unwrap().is_none());
+    let unknown = Burst { artifact: temp_dir.path().display().to_string(), ..burst };
+    assert!(record_burst(&logger, &unknown, false).await.unwrap().is_none());
+}
@@ -0,0 +1,115 @@
+// Build activity
+// Debounces watcher events into one record per burst of changes to an artifact
+
+use crate::db::store::BuildActivity;
+use crate::scanner::{self, SizeCache};
+use crate::tracking::logger::BuildLogger;
+use crate::tracking::watcher::WatchEvent;
+use crate::utils::logger::log_to_file;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// How long an artifact has to stay unchanged before its burst is recorded.
+pub const QUIET_SECONDS: i64 = 10;
+
+/// Changes to one artifact with no quiet gap between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Burst {
+    pub artifact: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub events: u64,
+}
+
+/// Bursts still open, by artifact.
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    open: HashMap<String, Burst>,
+}
+
+impl ActivityTracker {
+    /// Adds a change to `artifact` at `at`, starting a burst if none is open.
+    pub fn touch(&mut self, artifact: String, at: DateTime<Utc>) {
+        let burst = self.open.entry(artifact.clone()).or_insert(Burst { artifact, started_at: at, ended_at: at, events: 0 });
+        burst.ended_at = burst.ended_at.max(at);
+        burst.events += 1;
+    }
+
+    /// Closes the bursts that saw no change for `quiet` before `now`, oldest first.
+    pub fn settled(&mut self, now: DateTime<Utc>, quiet: Duration) -> Vec<Burst> {
+        let done: Vec<String> =
+            self.open.iter().filter(|(_, burst)| now - burst.ended_at >= quiet).map(|(artifact, _)| artifact.clone()).collect();
+        let mut bursts: Vec<Burst> = done.iter().filter_map(|artifact| self.open.remove(artifact)).collect();
+        bursts.sort_by_key(|burst| burst.started_at);
+        bursts
+    }
+}
+
+/// Measures the artifact after `burst` and records its new size and the
+/// activity. None when the artifact was deleted or is no longer recorded.
+pub async fn record_burst(
+    logger: &BuildLogger,
+    burst: &Burst,
+    one_file_system: bool,
+) -> Result<Option<BuildActivity>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(record) = logger.store.artifact(&burst.artifact).await? else { return Ok(None) };
+    // The build just changed this artifact, so none of its cached sizes are
+    // trusted; the walk only replaces the entries under it
+    let cache = SizeCache::default();
+    let path = burst.artifact.clone();
+    let (size, cache) = tokio::task::spawn_blocking(move || (scanner::measure(Path::new(&path), &cache, one_file_system), cache)).await?;
+    let Some(size) = size else { return Ok(None) };
+    logger
+        .log_build(&record.project_path, &record.language, &record.path, size, record.verified, record.description.as_deref())
+        .await?;
+    let activity = BuildActivity {
+        artifact_path: record.path,
+        started_at: burst.started_at,
+        ended_at: burst.ended_at,
+        events: burst.events,
+        size_before: Some(record.size_bytes),
+        size_after: size.apparent_bytes,
+    };
+    logger.store.record_activity(&activity).await?;
+    cache.save(logger.store.as_ref()).await?;
+    Ok(Some(activity))
+}
+
+/// Collects watcher events and records each burst once its artifact has been
+/// quiet for QUIET_SECONDS, handing the result to `on_activity`. Returns when
+/// the watcher goes away, after recording what was still open.
+pub async fn run(
+    logger: BuildLogger,
+    mut events: UnboundedReceiver<WatchEvent>,
+    one_file_system: bool,
+    on_activity: impl Fn(BuildActivity),
+) {
+    let mut tracker = ActivityTracker::default();
+    let quiet = Duration::seconds(QUIET_SECONDS);
+    let mut tick = tokio::time::interval(std::time::Duration::from_secs(1));
+    loop {
+        let closed = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => {
+                    tracker.touch(event.artifact, event.at);
+                    continue;
+                }
+                None => true,
+            },
+            _ = tick.tick() => false,
+        };
+        let now = if closed { DateTime::<Utc>::MAX_UTC } else { Utc::now() };
+        for burst in tracker.settled(now, quiet) {
+            match record_burst(&logger, &burst, one_file_system).await {
+                Ok(Some(activity)) => on_activity(activity),
+                Ok(None) => {}
+                Err(e) => log_to_file(&format!("Recording build activity for {} failed: {:?}", burst.artifact, e)),
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
@@ -1,19 +1,18 @@
 // Build logging functionality
 
-use crate::db::connection::establish_connection;
-use crate::db::schema::create_tables;
-use sqlx::PgPool;
+use crate::db::connection::open_store;
+use crate::db::store::{ArtifactStore, DiskUsage, ObservationOutcome};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct BuildLogger {
-    pub pool: PgPool,
+    pub store: Arc<dyn ArtifactStore>,
 }
 
 impl BuildLogger {
     pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
-        let pool = establish_connection(database_url).await?;
-        create_tables(&pool).await?;
-        Ok(BuildLogger { pool })
+        let store = open_store(database_url).await?;
+        Ok(BuildLogger { store })
     }
 
     pub async fn log_build(
@@ -21,19 +20,12 @@ impl BuildLogger {
         project_path: &str,
         language: &str,
         artifact_path: &str,
-        size: u64,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            "INSERT INTO builds (project_path, language, artifact_path, size_bytes) VALUES ($1, $2, $3, $4)",
-        )
-        .bind(project_path)
-        .bind(language)
-        .bind(artifact_path)
-        .bind(size as i64)
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+        size: DiskUsage,
+        verified: bool,
+        description: Option<&str>,
+    ) -> Result<ObservationOutcome, sqlx::Error> {
+        self.store
+            .record_observation(project_path, language, artifact_path, size, verified, description)
+            .await
     }
 }
-
-
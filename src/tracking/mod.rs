@@ -3,3 +3,4 @@
 
 pub mod logger;
 pub mod watcher;
+pub mod activity;
@@ -1,29 +1,52 @@
 // File system watcher for build artifacts
+// Reports changes under each watched artifact to whoever subscribed
 
 use notify::{RecommendedWatcher, RecursiveMode, Result as NotifyResult, Watcher};
+use std::collections::BTreeSet;
 use std::sync::{Arc, Mutex};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use crate::utils::logger::log_to_file;
 
+/// A change somewhere inside a watched artifact.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchEvent {
+    /// The watched artifact the changed path belongs to.
+    pub artifact: String,
+    pub at: DateTime<Utc>,
+}
+
 #[derive(Clone)]
 pub struct BuildWatcher {
     watcher: Arc<Mutex<RecommendedWatcher>>,
+    roots: Arc<Mutex<BTreeSet<PathBuf>>>,
+    events: Arc<Mutex<Option<UnboundedSender<WatchEvent>>>>,
 }
 
 impl BuildWatcher {
     pub fn new(debug_logs_enabled: bool) -> Self {
+        let roots: Arc<Mutex<BTreeSet<PathBuf>>> = Arc::new(Mutex::new(BTreeSet::new()));
+        let events: Arc<Mutex<Option<UnboundedSender<WatchEvent>>>> = Arc::new(Mutex::new(None));
+        let callback_roots = Arc::clone(&roots);
+        let callback_events = Arc::clone(&events);
         let watcher = RecommendedWatcher::new(
             move |res: Result<notify::Event, notify::Error>| match res {
                 Ok(event) => {
-                    if debug_logs_enabled {
-                        if matches!(
-                            event.kind,
-                            notify::EventKind::Create(_)
-                                | notify::EventKind::Modify(_)
-                                | notify::EventKind::Remove(_)
-                        ) {
+                    if matches!(
+                        event.kind,
+                        notify::EventKind::Create(_) | notify::EventKind::Modify(_) | notify::EventKind::Remove(_)
+                    ) {
+                        if debug_logs_enabled {
                             log_to_file(&format!("Build change detected: {:?}", event));
                         }
+                        if let Some(sender) = callback_events.lock().unwrap().as_ref() {
+                            let roots = callback_roots.lock().unwrap();
+                            let at = Utc::now();
+                            for artifact in event.paths.iter().filter_map(|path| artifact_of(&roots, path)) {
+                                let _ = sender.send(WatchEvent { artifact, at });
+                            }
+                        }
                     }
                 }
                 Err(e) => {
@@ -35,12 +58,30 @@ impl BuildWatcher {
             notify::Config::default(),
         )
         .unwrap();
-        BuildWatcher { watcher: Arc::new(Mutex::new(watcher)) }
+        BuildWatcher { watcher: Arc::new(Mutex::new(watcher)), roots, events }
     }
 
     pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> NotifyResult<()> {
         self.watcher.lock().unwrap()
             .watch(path.as_ref(), RecursiveMode::Recursive)?;
+        self.roots.lock().unwrap().insert(path.as_ref().to_path_buf());
         Ok(())
     }
+
+    /// Changes under watched artifacts from now on. A new subscriber replaces
+    /// the previous one.
+    pub fn subscribe(&self) -> UnboundedReceiver<WatchEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.events.lock().unwrap() = Some(tx);
+        rx
+    }
+}
+
+/// The innermost watched artifact containing `path`.
+fn artifact_of(roots: &BTreeSet<PathBuf>, path: &Path) -> Option<String> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.as_os_str().len())
+        .map(|root| root.display().to_string())
 }
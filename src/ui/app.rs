@@ -10,24 +10,44 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState, Padding, Paragraph},
 };
 use std::time::Duration;
-use crate::config::settings::{load_config, save_config};
+use crate::config::settings::save_config;
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use crate::tracking::logger::BuildLogger;
+use crate::tracking::activity;
 use crate::tracking::watcher::BuildWatcher;
-use crate::config::Config;
-use sqlx::{Row, types::chrono::{DateTime, Utc}};
+use crate::config::{Config, PartialConfig};
 use std::io;
+use crate::cleanup;
+use crate::cleanup::audit::{self, Outcome, TRIGGER_CLEAR_ALL, TRIGGER_MANUAL, TRIGGER_RETENTION};
+use crate::cleanup::guard::{PathGuard, is_gone};
+use crate::cleanup::lock::CleanupLock;
+use crate::cleanup::plan::{DeletionPlan, PlannedArtifact};
+use crate::cleanup::policy::RetentionPolicy;
+use crate::cleanup::quarantine;
+use crate::cleanup::remove::{RemovalReport, remove_tree_escalating};
 use std::path::Path;
-use walkdir::WalkDir;
-use crate::utils::{detect_language_for_path, calculate_dir_size};
+use crate::rules::RuleSet;
+use crate::scanner::{self, ScanProgress, SizeCache};
+use crate::utils::{format_size, format_size_change};
+use crate::utils::logger::log_to_file;
 use crate::ui::popup::{PopupState, PopupCommand};
+use crate::db::store::{ArtifactRecord, BuildActivity, Pin};
+use crate::utils::parse_age;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+const PIN_NOTE: &str = "Pin note";
+const PIN_EXPIRY: &str = "Pin for (e.g. 14d, empty: until unpinned)";
+const SUDO_PROMPT: &str = "Enter sudo password";
 
 pub struct App {
     pub should_quit: bool,
     pub artifacts: Vec<String>,
     pub scanning: bool,
     pub scanned: bool,
+    /// Counters and cancel flag of the latest scan.
+    pub scan_progress: Arc<ScanProgress>,
     pub selected: usize,
     pub focused_panel: usize,
     pub logger: BuildLogger,
@@ -35,28 +55,48 @@ pub struct App {
     pub total_builds: usize,
     pub chart_data: Vec<(String, u64)>,
     pub chart_selected: usize,
+    pub size_trend: Option<i64>,
+    pub tracking_since: Option<DateTime<Utc>>,
+    pub artifact_details: HashMap<String, ArtifactRecord>,
+    pub last_seen: Option<DateTime<Utc>>,
     pub watcher: BuildWatcher,
+    /// Latest burst of changes the watcher recorded.
+    pub last_activity: Option<BuildActivity>,
     pub automatic_removal: bool,
     pub config: Config,
     pub popup_state: PopupState,
     pub logs: Arc<Mutex<Vec<String>>>,
     pub pending_action: Option<String>,
     pub pending_failed_paths: Vec<String>,
+    /// What a removal waiting on a sudo password has done so far.
+    pub pending_report: RemovalReport,
+    /// The cleanup lock a removal waiting on a sudo password still holds.
+    pub pending_lock: Option<CleanupLock>,
+    /// Why the plan being executed picked each artifact, for the audit log.
+    pub deletion_reasons: HashMap<String, String>,
+    /// Path and note of a pin waiting on its expiry.
+    pub pending_pin: Option<(String, Option<String>)>,
     pub scan_result_tx: mpsc::Sender<Vec<String>>,
     pub scan_result_rx: mpsc::Receiver<Vec<String>>,
+    pub activity_rx: mpsc::UnboundedReceiver<BuildActivity>,
 }
 
 impl App {
-    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let config = load_config();
+    pub async fn new(config: Config) -> Result<Self, Box<dyn std::error::Error>> {
         let logger = BuildLogger::new(&config.database_url).await?;
         let watcher = BuildWatcher::new(config.debug_logs_enabled);
         let (tx, rx) = mpsc::channel(1);
+        // Bursts of changes under watched artifacts are recorded in the background
+        let (activity_tx, activity_rx) = mpsc::unbounded_channel();
+        tokio::spawn(activity::run(logger.clone(), watcher.subscribe(), config.one_file_system, move |activity| {
+            let _ = activity_tx.send(activity);
+        }));
         let mut app = App {
             should_quit: false,
             artifacts: vec![], // Start empty
             scanning: false,
             scanned: false,
+            scan_progress: Arc::new(ScanProgress::new()),
             selected: 0,
             focused_panel: 0,
             logger,
@@ -64,15 +104,25 @@ impl App {
             total_builds: 0,
             chart_data: vec![],
             chart_selected: 0,
+            size_trend: None,
+            tracking_since: None,
+            artifact_details: HashMap::new(),
+            last_seen: None,
             watcher,
+            last_activity: None,
             automatic_removal: true,
             config,
             popup_state: PopupState::None,
             logs: Arc::new(Mutex::new(vec![])),
             pending_action: None,
             pending_failed_paths: vec![],
+            pending_report: RemovalReport::default(),
+            pending_lock: None,
+            deletion_reasons: HashMap::new(),
+            pending_pin: None,
             scan_result_tx: tx,
             scan_result_rx: rx,
+            activity_rx,
         };
         app.load_artifacts().await;
         app.load_history().await;
@@ -100,38 +150,89 @@ impl App {
 
         // Check for scan completion
         if let Ok(artifacts) = self.scan_result_rx.try_recv() {
-            self.artifacts = artifacts;
             self.scanning = false;
             self.scanned = true;
-            self.popup_state = PopupState::Info { message: format!("Scan complete. Found {} artifacts.", self.artifacts.len()) };
-            let _ = self.load_history().await;
+            let cancelled = self.scan_progress.is_cancelled();
+            if cancelled {
+                // Keep what earlier scans found alongside the partial results
+                let found = artifacts.len();
+                for path in artifacts {
+                    if !self.artifacts.contains(&path) {
+                        self.artifacts.push(path);
+                    }
+                }
+                self.popup_state = PopupState::Info { message: format!("Scan cancelled. Found {} artifacts before stopping.", found) };
+            } else {
+                self.artifacts = artifacts;
+                self.popup_state = PopupState::Info { message: format!("Scan complete. Found {} artifacts.", self.artifacts.len()) };
+            }
+            self.load_history().await;
 
-            // Trigger automatic cleanup if enabled
-            if self.automatic_removal {
-                let pool = self.logger.pool.clone();
-                let retention_days = self.config.retention_days;
+            // Offer the retention pass after a complete scan; nothing goes before it is confirmed
+            if self.automatic_removal && !cancelled {
+                let rules = RuleSet::new(&self.config.rules);
+                match DeletionPlan::retention(self.logger.store.as_ref(), &RetentionPolicy::from_config(&self.config), &rules).await {
+                    Ok(plan) if !plan.is_empty() => {
+                        self.popup_state = PopupState::new_plan(plan, "🧹 AUTOMATIC REMOVAL".to_string(), TRIGGER_RETENTION);
+                    }
+                    Ok(_) => {}
+                    Err(e) => log_to_file(&format!("Automatic cleanup failed: {:?}", e)),
+                }
+                // Quarantined artifacts were confirmed when they were moved
+                let store = Arc::clone(&self.logger.store);
+                let quarantine_days = self.config.quarantine_days;
+                let guard = self.deletion_guard();
                 tokio::spawn(async move {
-                    // Get old artifact paths from database
-                    match crate::db::schema::get_old_artifact_paths(&pool, retention_days).await {
-                        Ok(old_paths) => {
-                            // Delete directories from disk
-                            for path in old_paths {
-                                let _ = std::fs::remove_dir_all(&path);
-                            }
-                            // Remove entries from database
-                            let _ = crate::db::schema::delete_old_builds_from_db(&pool, retention_days).await;
+                    // Skipped while the daemon or a plan is deleting; the next scan tries again
+                    let lock = match CleanupLock::acquire(&store, "tui").await {
+                        Ok(lock) => lock,
+                        Err(e) => {
+                            log_to_file(&format!("Automatic cleanup skipped: {}", e));
+                            return;
                         }
-                        Err(_) => {
-                            // Cleanup query failed, continue normally
+                    };
+                    match quarantine::purge_expired(store.as_ref(), quarantine_days, &guard).await {
+                        Ok(expired) => {
+                            for refusal in expired.refused {
+                                log_to_file(&format!("Automatic cleanup: {}", refusal));
+                            }
                         }
+                        Err(e) => log_to_file(&format!("Automatic cleanup failed: {:?}", e)),
+                    }
+                    if let Err(e) = lock.release(store.as_ref()).await {
+                        log_to_file(&format!("Releasing the cleanup lock failed: {:?}", e));
                     }
                 });
             }
         }
 
+        // Sizes and history follow builds as the watcher records them
+        let mut recorded = false;
+        while let Ok(activity) = self.activity_rx.try_recv() {
+            log_to_file(&format!(
+                "Build activity in {}: {} changes, {}",
+                activity.artifact_path,
+                activity.events,
+                format_size_change(activity.bytes_changed())
+            ));
+            recorded = true;
+        }
+        if recorded {
+            self.load_history().await;
+        }
+
+        // A sudo prompt dismissed with Esc gives up its removal and the lock
+        if self.pending_lock.is_some() && !self.awaiting_password() {
+            self.pending_action = None;
+            self.pending_failed_paths.clear();
+            if let Some(lock) = self.pending_lock.take() {
+                self.release_cleanup_lock(lock).await;
+            }
+        }
+
         // Use non-blocking poll with timeout to allow UI to redraw
-        if event::poll(Duration::from_millis(100)).unwrap_or(false) {
-            if let Ok(event) = event::read() {
+        if event::poll(Duration::from_millis(100)).unwrap_or(false)
+            && let Ok(event) = event::read() {
                 match event {
                     Event::Resize(_, _) => {
                         // Terminal was resized, UI will redraw automatically on next loop
@@ -139,7 +240,7 @@ impl App {
                     Event::Key(key) => {
             // Global Shift+D for clear all builds
             if key.code == KeyCode::Char('D') && key.modifiers.contains(KeyModifiers::SHIFT) {
-                self.popup_state = PopupState::new_clear_all_confirmation();
+                self.clear_all_builds().await;
                 return;
             }
             // Handle popup first
@@ -159,7 +260,7 @@ impl App {
                     PopupCommand::ToggleRemoval => {
                         if !self.automatic_removal {
                             // Show warning when enabling automatic removal
-                            let message = "⚠️  AUTOMATIC REMOVAL WILL DELETE OLD ARTIFACTS\n\nPlease verify your build directories in the list above.\nAny directories matching common build paths older than\nretention days will be permanently deleted.\n\nEnable automatic removal? (Enter: Yes, Esc: No)".to_string();
+                            let message = "⚠️  AUTOMATIC REMOVAL WILL DELETE OLD ARTIFACTS\n\nPlease verify your build directories in the list above.\nAfter each scan you will be shown the verified artifacts\nolder than retention days and can confirm or edit the\nplan. Unverified ones (❔) are never removed automatically.\n\nEnable automatic removal? (Enter: Yes, Esc: No)".to_string();
                             let action = "enable_automatic_removal".to_string();
                             self.popup_state = PopupState::ConfirmAction { message, action };
                         } else {
@@ -171,65 +272,48 @@ impl App {
                         if key == "Retention Days" {
                             if let Ok(days) = value.parse::<u32>() {
                                 self.config.retention_days = days;
+                                self.save_settings(PartialConfig { retention_days: Some(days), ..Default::default() });
                             }
                         } else if key == "Scan Path" {
                             self.config.scan_paths = vec![value];
-                         } else if key == "Enter sudo password" {
-                             if let Some(action) = self.pending_action.take() {
-                                 if action == "delete" {
-                                     let path = self.artifacts[self.selected].clone();
-                                     if self.delete_with_sudo(&path, Some(&value)) {
-                                         self.artifacts.remove(self.selected);
-                                         if self.selected >= self.artifacts.len() && self.selected > 0 {
-                                             self.selected -= 1;
-                                         }
-                                         // Update DB
-                                         let pool = self.logger.pool.clone();
-                                         let _ = tokio::spawn(async move {
-                                             let _ = sqlx::query("DELETE FROM builds WHERE artifact_path = $1").bind(&path).execute(&pool).await;
-                                         });
-                                         self.popup_state = PopupState::Info { message: "Artifact deleted with sudo.".to_string() };
-                                     } else {
-                                         self.popup_state = PopupState::Info { message: "Sudo delete failed.".to_string() };
-                                     }
-                                 } else if action == "clear_all" {
-                                     let failed_paths = self.pending_failed_paths.clone();
-                                     self.pending_failed_paths.clear();
-                                     let mut all_success = true;
-                                     for path in failed_paths {
-                                         if !self.delete_with_sudo(&path, Some(&value)) {
-                                             all_success = false;
-                                         }
-                                     }
-                                     if all_success {
-                                         self.artifacts.clear();
-                                         let _ = sqlx::query("DELETE FROM builds").execute(&self.logger.pool).await;
-                                         self.load_history().await;
-                                         self.popup_state = PopupState::Info { message: "All builds cleared with sudo.".to_string() };
-                                     } else {
-                                         self.popup_state = PopupState::Info { message: "Some sudo deletes failed.".to_string() };
-                                     }
+                            self.save_settings(PartialConfig { scan_paths: Some(self.config.scan_paths.clone()), ..Default::default() });
+                        } else if key == PIN_NOTE {
+                            if let Some((_, note)) = self.pending_pin.as_mut() {
+                                *note = Some(value.trim().to_string()).filter(|note| !note.is_empty());
+                                self.popup_state = PopupState::new_input(PIN_EXPIRY.to_string(), "".to_string());
+                            }
+                        } else if key == PIN_EXPIRY {
+                            self.pin_pending(&value).await;
+                         } else if key == SUDO_PROMPT
+                             && let Some(trigger) = self.pending_action.take().and_then(|action| action.strip_prefix("remove:").map(str::to_string)) {
+                                 let paths = std::mem::take(&mut self.pending_failed_paths);
+                                 let report = std::mem::take(&mut self.pending_report);
+                                 // Still held from the first attempt, so nobody else deleted in between
+                                 let lock = match self.pending_lock.take() {
+                                     Some(lock) => Some(lock),
+                                     None => self.cleanup_lock().await,
+                                 };
+                                 if let Some(lock) = lock {
+                                     self.remove_artifacts(paths, Some(&value), report, &trigger).await;
+                                     self.finish_removal(lock).await;
                                  }
-                             }
                         }
-                        // Save config after changes
-                        save_config(&self.config).ok();
                     }
                     PopupCommand::DeleteArtifact => {
-                        self.popup_state = PopupState::new_confirm_action("Delete this artifact?".to_string(), "delete".to_string());
+                        self.popup_state = PopupState::new_confirm_action(self.delete_prompt(), "delete".to_string());
                     }
                     PopupCommand::RebuildArtifact => {
                         self.popup_state = PopupState::new_confirm_action("Rebuild this project?".to_string(), "rebuild".to_string());
                     }
-                    PopupCommand::ClearAllBuilds => {
-                        self.clear_all_builds().await;
+                    PopupCommand::ExecutePlan { artifacts, trigger } => {
+                        self.execute_plan(artifacts, trigger).await;
                     }
                     PopupCommand::ConfirmAction { action } => {
                          if action.starts_with("remove_excluded:") {
                              let path = action.strip_prefix("remove_excluded:").unwrap_or("").to_string();
                              self.config.excluded_paths.retain(|p| p != &path);
-                             save_config(&self.config).ok();
-                             self.popup_state = PopupState::Info { message: format!("Removed from exclusion list. Rescanning...", ) };
+                             self.save_settings(PartialConfig { excluded_paths: Some(self.config.excluded_paths.clone()), ..Default::default() });
+                             self.popup_state = PopupState::Info { message: "Removed from exclusion list. Rescanning...".to_string() };
                              if !self.scanning {
                                  self.trigger_scan().await;
                              }
@@ -244,29 +328,35 @@ impl App {
                                     self.rebuild_selected();
                                     self.popup_state = PopupState::new_progress("Rebuilding project...".to_string());
                                 }
-                                "exclude" => {
-                                    if self.selected < self.artifacts.len() {
-                                        let path = self.artifacts[self.selected].clone();
-                                        self.config.excluded_paths.push(path);
-                                        self.artifacts.remove(self.selected);
-                                        if self.selected >= self.artifacts.len() && self.selected > 0 {
-                                            self.selected -= 1;
-                                        }
-                                        save_config(&self.config).ok();
-                                        self.popup_state = PopupState::Info { message: "Path added to exclusion list.".to_string() };
+                                "exclude" if self.selected < self.artifacts.len() => {
+                                    let path = self.artifacts[self.selected].clone();
+                                    self.config.excluded_paths.push(path);
+                                    self.artifacts.remove(self.selected);
+                                    if self.selected >= self.artifacts.len() && self.selected > 0 {
+                                        self.selected -= 1;
                                     }
+                                    self.save_settings(PartialConfig { excluded_paths: Some(self.config.excluded_paths.clone()), ..Default::default() });
+                                    self.popup_state = PopupState::Info { message: "Path added to exclusion list.".to_string() };
                                 }
+                                "unpin" => self.unpin_selected().await,
                                 "enable_automatic_removal" => {
                                     self.automatic_removal = true;
-                                    self.popup_state = PopupState::Info { message: "Automatic removal enabled. Old artifacts will be cleaned up after scans.".to_string() };
+                                    self.popup_state = PopupState::Info { message: "Automatic removal enabled. Old artifacts will be offered for cleanup after scans.".to_string() };
                                 }
                                 _ => {}
                             }
                          }
                     }
+                    PopupCommand::CancelScan => {
+                        self.scan_progress.cancel();
+                    }
                     PopupCommand::OpenExcludedPaths => {
                         self.popup_state = PopupState::new_excluded_paths(self.config.excluded_paths.clone());
                     }
+                    PopupCommand::OpenPolicies => self.open_policies(),
+                    PopupCommand::RestoreQuarantined { id } => {
+                        self.restore_quarantined(id).await;
+                    }
                 }
             } else if matches!(self.popup_state, PopupState::None) {
                 // Main keys only when no popup
@@ -280,22 +370,25 @@ impl App {
                     },
                     KeyCode::Char('q') => self.should_quit = true,
                     KeyCode::Tab => self.focused_panel = (self.focused_panel + 1) % 5,
-                    KeyCode::Char('s') => if !self.scanning { self.trigger_scan().await; },
-                     KeyCode::Char('d') => self.popup_state = PopupState::new_confirm_action("Delete this artifact?".to_string(), "delete".to_string()),
-                    KeyCode::Char('x') | KeyCode::Char('X') => {
-                        if self.focused_panel == 0 && self.selected < self.artifacts.len() {
-                            self.popup_state = PopupState::new_confirm_action("Exclude this path from scanning?".to_string(), "exclude".to_string());
-                        }
+                    KeyCode::Char('s') if !self.scanning => self.trigger_scan().await,
+                    KeyCode::Char('c') if self.scanning => self.scan_progress.cancel(),
+                     KeyCode::Char('d') => self.popup_state = PopupState::new_confirm_action(self.delete_prompt(), "delete".to_string()),
+                    KeyCode::Char('x') | KeyCode::Char('X') if self.focused_panel == 0 && self.selected < self.artifacts.len() => {
+                        self.popup_state = PopupState::new_confirm_action("Exclude this path from scanning?".to_string(), "exclude".to_string());
                     },
+                    KeyCode::Char('p') if self.focused_panel == 0 && self.selected < self.artifacts.len() => self.toggle_pin(),
                     KeyCode::Char('r') => self.rebuild_selected(),
                     KeyCode::Char('h') => self.load_history().await,
                     KeyCode::Char('e') => self.popup_state = PopupState::new_settings_list(),
                      KeyCode::Char('l') => self.popup_state = PopupState::new_logs_popup(Arc::clone(&self.logs)),
+                    KeyCode::Char('u') => self.open_quarantine().await,
+                    KeyCode::Char('a') => self.open_deletions().await,
                      KeyCode::Up | KeyCode::PageUp => {
                          if self.focused_panel == 0 && self.selected > 0 {
                              self.selected -= 1;
                          } else if self.focused_panel == 2 && self.chart_selected > 0 {
                              self.chart_selected -= 1;
+                             self.load_size_trend().await;
                          }
                      }
                      KeyCode::Down | KeyCode::PageDown => {
@@ -303,6 +396,7 @@ impl App {
                              self.selected += 1;
                          } else if self.focused_panel == 2 && self.chart_selected < self.chart_data.len().saturating_sub(1) {
                              self.chart_selected += 1;
+                             self.load_size_trend().await;
                          }
                      }
                     _ => {}
@@ -319,7 +413,6 @@ impl App {
                     }
                 }
             }
-        }
     }
 
     fn draw(&self, f: &mut Frame) {
@@ -348,7 +441,12 @@ impl App {
 
         self.popup_state.draw(f, size);
 
-        let footer = Paragraph::new("Tab: Focus | s: Scan | d: Delete | x: Exclude | r: Rebuild | e: Settings | l: Logs | Shift+D: Clear All | q: Quit")
+        let keys = if self.scanning {
+            "Tab: Focus | c: Cancel Scan | d: Delete"
+        } else {
+            "Tab: Focus | s: Scan | d: Delete"
+        };
+        let footer = Paragraph::new(format!("{} | x: Exclude | p: Pin | r: Rebuild | u: Quarantine | a: Audit | e: Settings | l: Logs | Shift+D: Clear All | q: Quit", keys))
             .style(Style::default().fg(Color::Black).bg(Color::LightGreen));
         f.render_widget(footer, chunks[2]);
     }
@@ -418,12 +516,38 @@ impl App {
                 } else {
                     Color::White
                 };
+                // Unverified matches are dimmed and never removed automatically
+                let verified = self.artifact_details.get(a).is_none_or(|record| record.verified);
+                let color = if verified { color } else { Color::DarkGray };
                 let style = if focused && i + start == self.selected {
                     Style::default().bg(Color::Blue).fg(Color::Black)
                 } else {
                     Style::default().fg(color)
                 };
-                ListItem::new(Span::styled(format!("📁 {}", relative_path), style))
+                let pin = self.artifact_details.get(a).and_then(|record| record.pin.as_ref()).filter(|pin| pin.is_active(Utc::now()));
+                let details = match self.artifact_details.get(a) {
+                    Some(record) => {
+                        let status = if record.verified { "" } else { ", unverified" };
+                        let mut description = record.description.as_ref().map(|d| format!(" · {}", d)).unwrap_or_default();
+                        if let Some(pin) = pin {
+                            description.push_str(&format!(" · {}", pin_label(pin)));
+                        }
+                        let on_disk = match record.disk_bytes {
+                            Some(disk) if disk != record.size_bytes => format!(", {}MB on disk", disk / 1_000_000),
+                            _ => String::new(),
+                        };
+                        format!(" [{}, {}MB{}{}]{}", record.language, record.size_bytes / 1_000_000, on_disk, status, description)
+                    }
+                    None => String::new(),
+                };
+                let icon = if pin.is_some() {
+                    "📌"
+                } else if verified {
+                    "📁"
+                } else {
+                    "❔"
+                };
+                ListItem::new(Span::styled(format!("{} {}{}", icon, relative_path, details), style))
             })
             .collect();
         let mut state = ListState::default();
@@ -470,7 +594,7 @@ impl App {
             // Calculate available width for bars: area.width - borders(2) - padding(2) - name(15) - spaces(2) - size(10)
             let available_width = area.width.saturating_sub(31).max(10) as u64;
             self.chart_data.iter().enumerate().map(|(i, (name, size))| {
-                let bar_len = if max_size > 0 { (size * available_width / max_size) as usize } else { 0 };
+                let bar_len = (size * available_width).checked_div(max_size).unwrap_or(0) as usize;
                 let bar = "█".repeat(bar_len);
                 let size_mb = size / 1_000_000;
                 let color = colors[i % colors.len()];
@@ -509,11 +633,18 @@ impl App {
         let masked_db = Self::mask_db_url(&self.config.database_url);
         let removal_status = if self.automatic_removal { "Enabled" } else { "Disabled" };
         let excluded_count = self.config.excluded_paths.len();
+        let policy = RetentionPolicy::from_config(&self.config);
+        let limits = policy.describe_limits().unwrap_or_else(|| "None".to_string());
+        let blocks: Vec<&str> = policy.blocks().map(|block| block.name.as_str()).collect();
+        let blocks = if blocks.is_empty() { "None".to_string() } else { blocks.join(", ") };
         let text = format!(
-            "DB: {}\nPaths: {}\nRetention Days: {}\nAutomatic Removal: {}\nExcluded Paths: {}",
+            "DB: {} ({})\nPaths: {}\nRetention Days: {}\nLimits: {}\nPolicies: {}\nAutomatic Removal: {}\nExcluded Paths: {}",
             masked_db,
+            self.logger.store.backend_name(),
             self.config.scan_paths.join(","),
             self.config.retention_days,
+            limits,
+            blocks,
             removal_status,
             excluded_count
         );
@@ -535,10 +666,38 @@ impl App {
         } else {
             Style::default()
         };
+        let trend = match self.size_trend {
+            Some(delta) if delta >= 0 => format!("+{}MB", delta / 1_000_000),
+            Some(delta) => format!("-{}MB", delta.unsigned_abs() / 1_000_000),
+            None => "n/a".to_string(),
+        };
+        let format_time = |t: Option<DateTime<Utc>>| t.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "never".to_string());
+        let (apparent, on_disk) = self.artifact_details.values().fold((0, 0), |(apparent, on_disk), record| {
+            (apparent + record.size_bytes, on_disk + record.disk_bytes.unwrap_or(record.size_bytes))
+        });
+        let watcher = match &self.last_activity {
+            Some(activity) => format!(
+                "last build in {} at {} ({})",
+                Path::new(&activity.artifact_path)
+                    .parent()
+                    .and_then(|project| project.file_name())
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default(),
+                format_time(Some(activity.ended_at)),
+                format_size_change(activity.bytes_changed())
+            ),
+            None => "Running".to_string(),
+        };
         let summary = format!(
-            "🏗️ Total Builds: {}\n📦 Artifacts: {}\n🔍 Scans: Active\n⚡ Watcher: Running",
+            "🏗️ Total Builds: {}\n📦 Artifacts: {}\n💾 Reclaimable: {} on disk ({} apparent)\n📈 Selected Growth: {}\n🗓️ Tracking Since: {}\n🔍 Last Seen: {}\n⚡ Watcher: {}",
             self.total_builds,
-            self.artifacts.len()
+            self.artifacts.len(),
+            format_size(on_disk),
+            format_size(apparent),
+            trend,
+            format_time(self.tracking_since),
+            format_time(self.last_seen),
+            watcher
         );
         let para = Paragraph::new(summary).block(
             Block::default()
@@ -554,144 +713,89 @@ impl App {
 
     async fn trigger_scan(&mut self) {
         self.scanning = true;
-        self.popup_state = PopupState::Scanning { logs: Arc::clone(&self.logs) };
+        self.scan_progress = Arc::new(ScanProgress::new());
+        self.popup_state = PopupState::Scanning { logs: Arc::clone(&self.logs), progress: Arc::clone(&self.scan_progress) };
         let scan_paths = if self.config.scan_paths.is_empty() {
             vec![".".to_string()]
         } else {
             self.config.scan_paths.clone()
         };
+        let roots = scanner::ScanRoot::from_config(&scan_paths, &self.config);
         let excluded_paths = self.config.excluded_paths.clone();
+        let rules = RuleSet::new(&self.config.rules);
         let logs_clone = Arc::clone(&self.logs);
-        let artifacts_clone = Arc::new(Mutex::new(vec![]));
-        let _artifacts_clone2 = Arc::clone(&artifacts_clone);
         let logger_clone = self.logger.clone();
         let mut watcher_clone = self.watcher.clone();
-        let _config_clone = self.config.clone();
         let tx_clone = self.scan_result_tx.clone();
+        let progress = Arc::clone(&self.scan_progress);
         tokio::spawn(async move {
-            {
-                let mut logs = logs_clone.lock().unwrap();
-                logs.push("Starting scan...".to_string());
+            let scan_logs = Arc::clone(&logs_clone);
+            let cache = SizeCache::load(logger_clone.store.as_ref()).await;
+            let (found, cache) = tokio::task::spawn_blocking(move || {
+                let found =
+                    scanner::scan(&roots, &excluded_paths, &rules, &cache, &progress, |line| scan_logs.lock().unwrap().push(line));
+                (found, cache)
+            })
+            .await
+            .unwrap_or_default();
+            // Log to DB
+            scanner::record(&logger_clone, &found).await;
+            if let Err(e) = cache.save(logger_clone.store.as_ref()).await {
+                log_to_file(&format!("Saving the size cache failed: {:?}", e));
             }
-            let common_dirs = [
-                // Rust
-                "target",
-                // C/C++
-                "build",
-                ".build",
-                "cmake-build-debug",
-                "cmake-build-release",
-                "Debug",
-                "Release",
-                // JavaScript/TypeScript
-                "node_modules",
-                "dist",
-                ".next",
-                ".parcel-cache",
-                ".cache",
-                // Python
-                "__pycache__",
-                ".eggs",
-                "eggs",
-                // Java/Gradle
-                ".gradle",
-                // PHP/Composer
-                "vendor",
-                // Ruby
-                ".bundle",
-                // General build outputs
-                "out",
-                ".output",
-                ".nyc_output",
-            ];
-            let mut total_count = 0;
-            for scan_path in scan_paths {
-                {
-                    let mut logs = logs_clone.lock().unwrap();
-                    logs.push(format!("Scanning path: {}", scan_path));
-                }
-                let mut count = 0;
-                for entry in WalkDir::new(&scan_path)
-                    .max_depth(3)
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                {
-                    if entry.file_type().is_dir() {
-                        let name = entry.file_name().to_string_lossy();
-                        let path_str = entry.path().display().to_string();
-
-                        // Check if path is in excluded list
-                        let is_excluded = excluded_paths.iter().any(|ex| path_str.contains(ex));
-
-                        if common_dirs.contains(&name.as_ref()) && !is_excluded {
-                            let project_path = entry.path().parent().unwrap_or(Path::new(".")).display().to_string();
-                            let language = detect_language_for_path(&project_path);
-                            let size = calculate_dir_size(&path_str);
-                            {
-                                let mut artifacts = artifacts_clone.lock().unwrap();
-                                artifacts.push(path_str.clone());
-                            }
-                            count += 1;
-                            // Log to DB
-                            let _ = logger_clone
-                                .log_build(&project_path, &language, &path_str, size)
-                                .await;
-                            // Start watching
-                            let _ = watcher_clone.watch(&path_str);
-                        }
-                    }
-                }
-                total_count += count;
-                {
-                    let mut logs = logs_clone.lock().unwrap();
-                    logs.push(format!("Scan complete for {}. Found {} artifacts.", scan_path, count));
-                }
+            // Start watching
+            for artifact in &found {
+                let _ = watcher_clone.watch(&artifact.path);
             }
-            let artifacts = artifacts_clone.lock().unwrap().clone();
+            let artifacts = found.into_iter().map(|a| a.path).collect();
             let _ = tx_clone.send(artifacts).await;
-            {
-                let mut logs = logs_clone.lock().unwrap();
-                logs.push(format!("Total scan complete. Found {} artifacts.", total_count));
-            }
         });
     }
 
+    /// Confirmation text for deleting the selected artifact, with a warning when
+    /// it is pinned or no fingerprint proved it is build output.
+    fn delete_prompt(&self) -> String {
+        let record = self.artifacts.get(self.selected).and_then(|path| self.artifact_details.get(path));
+        let unverified = record.is_some_and(|record| !record.verified);
+        if let Some(pin) = record.and_then(|record| record.pin.as_ref()).filter(|pin| pin.is_active(Utc::now())) {
+            format!("⚠️  This artifact is {}.\nDelete it anyway?", pin_label(pin))
+        } else if unverified {
+            "⚠️  This directory was not verified as build output.\nDelete it anyway?".to_string()
+        } else {
+            "Delete this artifact?".to_string()
+        }
+    }
+
     async fn delete_selected(&mut self) {
         if self.artifacts.is_empty() {
             return;
         }
         let path = self.artifacts[self.selected].clone();
         // Check for unusual files (e.g., bundle or many binaries)
-        if self.has_unusual_files(&path) {
+        if cleanup::has_unusual_files(&path) {
+            self.popup_state = PopupState::Info {
+                message: format!(
+                    "Not deleted: {} contains bundles or executables (.exe, .bin) that may not be build output.\nRemove it by hand if you are sure.",
+                    path
+                ),
+            };
             return;
         }
-        // Try sudo -n first
-        if self.delete_with_sudo(&path, None) {
-            self.artifacts.remove(self.selected);
-            if self.selected >= self.artifacts.len() && self.selected > 0 {
-                self.selected -= 1;
-            }
-            // Update DB
-            let _ = sqlx::query("DELETE FROM builds WHERE artifact_path = $1").bind(&path).execute(&self.logger.pool).await;
-            self.popup_state = PopupState::Info { message: "Artifact deleted.".to_string() };
+        let Some(lock) = self.cleanup_lock().await else { return };
+        self.deletion_reasons.clear();
+        if self.config.quarantine {
+            self.quarantine_artifacts(vec![path], TRIGGER_MANUAL).await;
         } else {
-            // Prompt for password
-            self.pending_action = Some("delete".to_string());
-            self.popup_state = PopupState::new_input("Enter sudo password".to_string(), "".to_string());
+            self.remove_artifacts(vec![path], None, RemovalReport::default(), TRIGGER_MANUAL).await;
         }
+        self.finish_removal(lock).await;
     }
 
     async fn load_artifacts(&mut self) {
-        // Query DB for recent artifact paths
-        match sqlx::query("SELECT artifact_path FROM builds GROUP BY artifact_path ORDER BY MAX(build_time) DESC LIMIT 50")
-            .fetch_all(&self.logger.pool)
-            .await
-        {
-            Ok(rows) => {
-                for row in rows {
-                    let path: String = row.get(0);
-                    self.artifacts.push(path.clone());
-                }
+        // Query DB for currently known artifacts
+        match self.logger.store.current_artifacts().await {
+            Ok(records) => {
+                self.artifacts.extend(records.into_iter().map(|r| r.path));
             }
             Err(_) => {
                 // Ignore errors, start empty
@@ -700,19 +804,24 @@ impl App {
     }
 
     async fn load_history(&mut self) {
-        // Query DB for build history
-        match sqlx::query("SELECT project_path, language, build_time FROM builds ORDER BY build_time DESC LIMIT 10")
-            .fetch_all(&self.logger.pool)
-            .await
-        {
-            Ok(rows) => {
-                self.build_history = rows
+        // Query DB for build events
+        match self.logger.store.build_events(10).await {
+            Ok(events) => {
+                self.build_history = events
                     .into_iter()
-                    .map(|row| {
-                        let project: String = row.get(0);
-                        let language: String = row.get(1);
-                        let time: DateTime<Utc> = row.get(2);
-                        format!("{} - {} - {}", project, language, time.format("%Y-%m-%d %H:%M"))
+                    .map(|event| {
+                        let size = match event.size_before {
+                            Some(before) => format!("{}MB → {}MB", before / 1_000_000, event.size_after / 1_000_000),
+                            None => format!("{}MB", event.size_after / 1_000_000),
+                        };
+                        format!(
+                            "{} - {} - {} ({}, {})",
+                            event.artifact_path,
+                            event.language,
+                            event.event_time.format("%Y-%m-%d %H:%M"),
+                            event.kind,
+                            size
+                        )
                     })
                     .collect();
             }
@@ -720,31 +829,47 @@ impl App {
                 self.build_history = vec!["Failed to load history".to_string()];
             }
         }
-        match sqlx::query("SELECT COUNT(*) FROM builds")
-            .fetch_one(&self.logger.pool)
-            .await
-        {
-            Ok(row) => {
-                self.total_builds = row.get::<i64, _>(0) as usize;
+        match self.logger.store.current_artifacts().await {
+            Ok(records) => {
+                self.tracking_since = records.iter().map(|r| r.first_seen).min();
+                self.last_seen = records.iter().map(|r| r.last_seen).max();
+                self.artifact_details = records.into_iter().map(|r| (r.path.clone(), r)).collect();
+            }
+            Err(_) => {
+                self.tracking_since = None;
+                self.last_seen = None;
+                self.artifact_details.clear();
+            }
+        }
+        self.last_activity = self.logger.store.build_activity(1).await.ok().and_then(|activity| activity.into_iter().next());
+        match self.logger.store.total_builds().await {
+            Ok(count) => {
+                self.total_builds = count as usize;
             }
             Err(_) => {
                 self.total_builds = 0;
             }
         }
-        match sqlx::query("SELECT artifact_path, MAX(size_bytes) as size FROM builds GROUP BY artifact_path ORDER BY size DESC")
-            .fetch_all(&self.logger.pool)
-            .await
-        {
-            Ok(rows) => {
-                self.chart_data = rows
-                    .into_iter()
-                    .map(|row| (row.get(0), row.get::<i64, _>(1) as u64))
-                    .collect();
+        match self.logger.store.size_chart().await {
+            Ok(data) => {
+                self.chart_data = data;
             }
             Err(_) => {
                 self.chart_data = vec![];
             }
         }
+        self.load_size_trend().await;
+    }
+
+    async fn load_size_trend(&mut self) {
+        // Size change of the selected chart entry since it was first observed
+        self.size_trend = None;
+        if let Some((path, _)) = self.chart_data.get(self.chart_selected)
+            && let Ok(history) = self.logger.store.size_history(path).await
+            && let (Some(first), Some(last)) = (history.first(), history.last())
+        {
+            self.size_trend = Some(last.size_bytes as i64 - first.size_bytes as i64);
+        }
     }
 
     fn mask_db_url(url: &str) -> String {
@@ -760,26 +885,17 @@ impl App {
         }
     }
 
-    fn has_unusual_files(&self, path: &str) -> bool {
-        if let Ok(entries) = std::fs::read_dir(path) {
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_lowercase();
-                if name.contains("bundle") || name.ends_with(".exe") || name.ends_with(".bin") {
-                    return true;
-                }
-            }
-        }
-        false
-    }
-
     fn rebuild_selected(&mut self) {
         if self.artifacts.is_empty() {
             return;
         }
         let artifact_path = &self.artifacts[self.selected];
-        let project_root = std::path::Path::new(artifact_path)
-            .parent()
-            .unwrap_or(std::path::Path::new("."));
+        let project_root = match self.artifact_details.get(artifact_path) {
+            Some(record) => std::path::Path::new(&record.project_path),
+            None => std::path::Path::new(artifact_path)
+                .parent()
+                .unwrap_or(std::path::Path::new(".")),
+        };
         // Detect build system
         if project_root.join("Cargo.toml").exists() {
             std::process::Command::new("sh")
@@ -799,44 +915,286 @@ impl App {
         // Add more as needed
     }
 
+    /// Shows the plan for deleting every artifact; nothing is deleted until it is confirmed.
     async fn clear_all_builds(&mut self) {
-        let mut failed_paths = vec![];
-        for path in self.artifacts.clone() {
-            if !self.delete_with_sudo(&path, None) {
-                failed_paths.push(path);
-            }
-        }
-        if failed_paths.is_empty() {
-            self.artifacts.clear();
-            let _ = sqlx::query("DELETE FROM builds").execute(&self.logger.pool).await;
-            self.load_history().await;
-            self.popup_state = PopupState::Info { message: "All builds cleared.".to_string() };
+        let rules = RuleSet::new(&self.config.rules);
+        self.popup_state = match DeletionPlan::clear_all(self.logger.store.as_ref(), &rules).await {
+            Ok(plan) => PopupState::new_plan(plan, "🔴 CLEAR ALL BUILDS".to_string(), TRIGGER_CLEAR_ALL),
+            Err(e) => PopupState::Info { message: format!("Could not build the deletion plan: {}", e) },
+        };
+    }
+
+    /// Deletes or quarantines the artifacts selected in a confirmed plan.
+    async fn execute_plan(&mut self, artifacts: Vec<PlannedArtifact>, trigger: &str) {
+        let Some(lock) = self.cleanup_lock().await else { return };
+        self.deletion_reasons =
+            artifacts.iter().filter_map(|artifact| Some((artifact.path.clone(), artifact.reason.clone()?))).collect();
+        let paths = artifacts.into_iter().map(|artifact| artifact.path).collect();
+        if self.config.quarantine {
+            self.quarantine_artifacts(paths, trigger).await;
         } else {
-            self.pending_failed_paths = failed_paths;
-            self.pending_action = Some("clear_all".to_string());
-            self.popup_state = PopupState::new_input("Enter sudo password".to_string(), "".to_string());
+            self.remove_artifacts(paths, None, RemovalReport::default(), trigger).await;
         }
+        self.finish_removal(lock).await;
     }
 
-    fn delete_with_sudo(&self, path: &str, password: Option<&str>) -> bool {
-        use std::process::Command;
-        let mut cmd = Command::new("sudo");
-        if password.is_some() {
-            cmd.arg("-S");
+    /// Releases the cleanup lock after a removal, unless it waits on a sudo
+    /// password; the retry then keeps the same lock.
+    async fn finish_removal(&mut self, lock: CleanupLock) {
+        if self.awaiting_password() {
+            self.pending_lock = Some(lock);
         } else {
-            cmd.arg("-n");
-        }
-        cmd.arg("rm").arg("-rf").arg(path);
-        if let Some(pwd) = password {
-            cmd.stdin(std::process::Stdio::piped());
-            let mut child = cmd.spawn().unwrap();
-            if let Some(mut stdin) = child.stdin.take() {
-                use std::io::Write;
-                let _ = stdin.write_all(format!("{}\n", pwd).as_bytes());
+            self.release_cleanup_lock(lock).await;
+        }
+    }
+
+    fn awaiting_password(&self) -> bool {
+        matches!(&self.popup_state, PopupState::Input { title, .. } if title == SUDO_PROMPT)
+    }
+
+    /// Takes the cleanup lock before deleting anything, or says who holds it.
+    async fn cleanup_lock(&mut self) -> Option<CleanupLock> {
+        match CleanupLock::acquire(&self.logger.store, "tui").await {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                self.popup_state = PopupState::Info { message: format!("Nothing was deleted: {}.\nTry again once it is done.", e) };
+                None
+            }
+        }
+    }
+
+    async fn release_cleanup_lock(&self, lock: CleanupLock) {
+        if let Err(e) = lock.release(self.logger.store.as_ref()).await {
+            log_to_file(&format!("Releasing the cleanup lock failed: {:?}", e));
+        }
+    }
+
+    /// Moves `paths` into quarantine, forgetting them as artifacts, and shows how
+    /// many were moved and why any were not.
+    async fn quarantine_artifacts(&mut self, paths: Vec<String>, trigger: &str) {
+        let guard = self.deletion_guard();
+        let mut moved = 0;
+        let mut errors = vec![];
+        for path in paths {
+            if is_gone(Path::new(&path)) {
+                self.audit(&path, trigger, Outcome::AlreadyGone).await;
+                let _ = self.logger.store.delete_artifact(&path).await;
+            } else if let Err(e) = quarantine::quarantine(self.logger.store.as_ref(), &guard, &path).await {
+                log_to_file(&e.to_string());
+                let outcome = match &e {
+                    quarantine::QuarantineError::Refused(refusal) => Outcome::refused(refusal),
+                    e => Outcome::Failed(e.to_string()),
+                };
+                self.audit(&path, trigger, outcome).await;
+                errors.push(e);
+                continue;
+            } else {
+                self.audit(&path, trigger, Outcome::Quarantined).await;
+                moved += 1;
+            }
+            self.artifacts.retain(|artifact| artifact != &path);
+        }
+        self.selected = self.selected.min(self.artifacts.len().saturating_sub(1));
+        self.load_history().await;
+
+        let mut lines = vec![format!(
+            "Moved {} artifacts to quarantine.\nPress u to restore them within {} days.",
+            moved, self.config.quarantine_days
+        )];
+        if let Some(first) = errors.first() {
+            if errors.len() > 1 {
+                lines.push(format!("{} could not be moved, see the log. First:", errors.len()));
             }
-            child.wait().unwrap().success()
+            lines.push(first.to_string());
+        }
+        self.popup_state = PopupState::Info { message: lines.join("\n") };
+    }
+
+    /// Pins the selected artifact after asking for a note and an expiry, or
+    /// offers to unpin it when it already is.
+    fn toggle_pin(&mut self) {
+        let path = self.artifacts[self.selected].clone();
+        let pinned = self.artifact_details.get(&path).is_some_and(|record| record.is_pinned(Utc::now()));
+        if pinned {
+            self.popup_state = PopupState::new_confirm_action("Unpin this artifact?".to_string(), "unpin".to_string());
         } else {
-            cmd.status().unwrap().success()
+            self.pending_pin = Some((path, None));
+            self.popup_state = PopupState::new_input(PIN_NOTE.to_string(), "".to_string());
+        }
+    }
+
+    async fn pin_pending(&mut self, expiry: &str) {
+        let Some((path, note)) = self.pending_pin.take() else { return };
+        let days = match expiry.trim() {
+            "" => None,
+            expiry => match parse_age(expiry) {
+                Ok(days) => Some(days),
+                Err(e) => {
+                    self.popup_state = PopupState::Info { message: e };
+                    return;
+                }
+            },
+        };
+        let pin = Pin::new(path, note, days, Utc::now());
+        let message = match self.logger.store.pin(&pin).await {
+            Ok(()) => format!("Pinned {}.\nBulk deletion and retention will skip it.", pin.path),
+            Err(e) => format!("Could not pin {}: {}", pin.path, e),
+        };
+        self.load_history().await;
+        self.popup_state = PopupState::Info { message };
+    }
+
+    async fn unpin_selected(&mut self) {
+        let Some(path) = self.artifacts.get(self.selected).cloned() else { return };
+        let message = match self.logger.store.unpin(&path).await {
+            Ok(_) => format!("Unpinned {}.", path),
+            Err(e) => format!("Could not unpin {}: {}", path, e),
+        };
+        self.load_history().await;
+        self.popup_state = PopupState::Info { message };
+    }
+
+    /// Lists the policy blocks and which one applies to each known artifact.
+    fn open_policies(&mut self) {
+        let policy = RetentionPolicy::from_config(&self.config);
+        let blocks = policy.blocks().map(|block| format!("{} ({})", block.name, block.summary())).collect();
+        let mut artifacts: Vec<(String, String)> =
+            self.artifact_details.values().map(|record| (policy.describe_for(record), record.path.clone())).collect();
+        artifacts.sort_by(|a, b| a.1.cmp(&b.1));
+        self.popup_state = PopupState::new_policies(blocks, artifacts);
+    }
+
+    async fn open_deletions(&mut self) {
+        match self.logger.store.deletions(None, 200).await {
+            Ok(records) => self.popup_state = PopupState::new_deletions(records),
+            Err(e) => self.popup_state = PopupState::Info { message: format!("Could not load the deletion log: {}", e) },
+        }
+    }
+
+    /// Writes a deletion to the audit log; the deletion itself already happened,
+    /// so a failure is only logged.
+    async fn audit(&self, path: &str, trigger: &str, outcome: Outcome) {
+        let reason = self.deletion_reasons.get(path).map(String::as_str);
+        let deletion = audit::deletion(path, self.artifact_details.get(path), trigger, &outcome, reason);
+        if let Err(e) = self.logger.store.record_deletion(&deletion).await {
+            log_to_file(&format!("Recording the deletion of {} failed: {:?}", path, e));
         }
     }
+
+    async fn open_quarantine(&mut self) {
+        match self.logger.store.quarantined().await {
+            Ok(entries) => self.popup_state = PopupState::new_quarantine(entries, self.config.quarantine_days),
+            Err(e) => self.popup_state = PopupState::Info { message: format!("Could not load the quarantine: {}", e) },
+        }
+    }
+
+    async fn restore_quarantined(&mut self, id: i64) {
+        let entry = match self.logger.store.quarantined().await {
+            Ok(entries) => entries.into_iter().find(|entry| entry.id == id),
+            Err(e) => {
+                self.popup_state = PopupState::Info { message: format!("Could not load the quarantine: {}", e) };
+                return;
+            }
+        };
+        let Some(entry) = entry else {
+            return;
+        };
+        let message = match quarantine::restore(self.logger.store.as_ref(), &entry).await {
+            Ok(()) => {
+                if !self.artifacts.contains(&entry.original_path) {
+                    self.artifacts.push(entry.original_path.clone());
+                }
+                self.load_history().await;
+                format!("Restored {}.", entry.original_path)
+            }
+            Err(e) => e.to_string(),
+        };
+        self.popup_state = PopupState::Info { message };
+    }
+
+    /// Removes `paths` in-process, forgetting each artifact once nothing of it is left,
+    /// and shows what was removed and what was left. Paths only sudo could finish are
+    /// kept with `report` so far for a password prompt, and only audited once they are settled.
+    async fn remove_artifacts(&mut self, paths: Vec<String>, password: Option<&str>, mut report: RemovalReport, trigger: &str) {
+        let guard = self.deletion_guard();
+        // Leftovers of an earlier attempt are retried below
+        report.failures.clear();
+        report.escalation_error = None;
+        let mut refusals = vec![];
+        let mut denied = vec![];
+        for path in paths {
+            if is_gone(Path::new(&path)) {
+                self.audit(&path, trigger, Outcome::AlreadyGone).await;
+            } else {
+                let resolved = match guard.check(&path) {
+                    Ok(resolved) => resolved,
+                    Err(refusal) => {
+                        log_to_file(&refusal.to_string());
+                        self.audit(&path, trigger, Outcome::refused(&refusal)).await;
+                        refusals.push(refusal);
+                        continue;
+                    }
+                };
+                let removal = remove_tree_escalating(&resolved, password);
+                let outcome = Outcome::of_removal(&removal);
+                if password.is_none() && removal.needs_password() {
+                    denied.push(path.clone());
+                } else {
+                    self.audit(&path, trigger, outcome.clone()).await;
+                }
+                report.merge(removal);
+                if !outcome.is_gone() {
+                    continue;
+                }
+            }
+            self.artifacts.retain(|artifact| artifact != &path);
+            let _ = self.logger.store.delete_artifact(&path).await;
+        }
+        self.selected = self.selected.min(self.artifacts.len().saturating_sub(1));
+        self.load_history().await;
+
+        if !denied.is_empty() {
+            self.pending_failed_paths = denied;
+            self.pending_report = report;
+            self.pending_action = Some(format!("remove:{}", trigger));
+            self.popup_state = PopupState::new_input(SUDO_PROMPT.to_string(), "".to_string());
+            return;
+        }
+        let mut lines = vec![];
+        let attempted = report.removed_files + report.removed_dirs > 0 || !report.escalated.is_empty() || !report.is_complete();
+        if attempted || refusals.is_empty() {
+            lines.push(report.summary());
+        }
+        if let Some(first) = refusals.first() {
+            if refusals.len() > 1 {
+                lines.push(format!("Refused {} paths, see the log. First:", refusals.len()));
+            }
+            lines.push(first.to_string());
+        }
+        self.popup_state = PopupState::Info { message: lines.join("\n") };
+    }
+
+    /// Saves the settings edited in the TUI to the config file.
+    fn save_settings(&self, changes: PartialConfig) {
+        if let Err(e) = save_config(&changes) {
+            log_to_file(&format!("Saving the config failed: {:?}", e));
+        }
+    }
+
+    /// Deletions must stay inside the configured scan paths.
+    fn deletion_guard(&self) -> PathGuard {
+        PathGuard::new(&self.config.scan_paths)
+    }
+}
+
+/// "pinned", with the note and expiry when there are any, e.g. "pinned until 2025-03-01: release build".
+fn pin_label(pin: &Pin) -> String {
+    let mut label = "pinned".to_string();
+    if let Some(expires_at) = pin.expires_at {
+        label.push_str(&format!(" until {}", expires_at.format("%Y-%m-%d")));
+    }
+    if let Some(note) = &pin.note {
+        label.push_str(&format!(": {}", note));
+    }
+    label
 }
@@ -8,6 +8,11 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Padding, Paragraph},
     crossterm::event::KeyCode,
 };
+use crate::cleanup::plan::{DeletionPlan, PlannedArtifact};
+use crate::db::store::{DeletionRecord, QuarantineEntry};
+use crate::scanner::progress::ProgressSnapshot;
+use crate::scanner::ScanProgress;
+use crate::utils::format_size;
 
 pub enum PopupCommand {
     OpenInput { title: String, initial: String },
@@ -16,9 +21,12 @@ pub enum PopupCommand {
     SetValue { key: String, value: String },
     DeleteArtifact,
     RebuildArtifact,
-    ClearAllBuilds,
+    ExecutePlan { artifacts: Vec<PlannedArtifact>, trigger: &'static str },
     ConfirmAction { action: String },
     OpenExcludedPaths,
+    OpenPolicies,
+    CancelScan,
+    RestoreQuarantined { id: i64 },
 }
 
 pub enum PopupState {
@@ -27,13 +35,18 @@ pub enum PopupState {
     Input { title: String, input: String },
     DirBrowse { path: String, items: Vec<String>, selected: usize },
     Logs { logs: std::sync::Arc<std::sync::Mutex<Vec<String>>> },
-    Scanning { logs: std::sync::Arc<std::sync::Mutex<Vec<String>>> },
+    Scanning { logs: std::sync::Arc<std::sync::Mutex<Vec<String>>>, progress: std::sync::Arc<ScanProgress> },
     ArtifactActions { selected: usize },
-    ClearAllConfirmation,
+    /// `trigger` is what the audit log records the plan's deletions under.
+    Plan { plan: DeletionPlan, title: String, trigger: &'static str, selected: usize },
     ConfirmAction { message: String, action: String },
     Progress { message: String },
     Info { message: String },
     ExcludedPathsList { paths: Vec<String>, selected: usize },
+    Quarantine { entries: Vec<QuarantineEntry>, grace_days: u32, selected: usize },
+    Deletions { records: Vec<DeletionRecord>, selected: usize },
+    /// The `[[policies]]` blocks in order, and each artifact with the one that applies to it.
+    Policies { blocks: Vec<String>, artifacts: Vec<(String, String)>, selected: usize },
 }
 
 impl PopupState {
@@ -59,8 +72,8 @@ impl PopupState {
         PopupState::ArtifactActions { selected: 0 }
     }
 
-    pub fn new_clear_all_confirmation() -> Self {
-        PopupState::ClearAllConfirmation
+    pub fn new_plan(plan: DeletionPlan, title: String, trigger: &'static str) -> Self {
+        PopupState::Plan { plan, title, trigger, selected: 0 }
     }
 
     pub fn new_confirm_action(message: String, action: String) -> Self {
@@ -74,6 +87,18 @@ impl PopupState {
     pub fn new_excluded_paths(paths: Vec<String>) -> Self {
         PopupState::ExcludedPathsList { paths, selected: 0 }
     }
+
+    pub fn new_quarantine(entries: Vec<QuarantineEntry>, grace_days: u32) -> Self {
+        PopupState::Quarantine { entries, grace_days, selected: 0 }
+    }
+
+    pub fn new_deletions(records: Vec<DeletionRecord>) -> Self {
+        PopupState::Deletions { records, selected: 0 }
+    }
+
+    pub fn new_policies(blocks: Vec<String>, artifacts: Vec<(String, String)>) -> Self {
+        PopupState::Policies { blocks, artifacts, selected: 0 }
+    }
 }
 
 impl PopupState {
@@ -82,7 +107,7 @@ impl PopupState {
             PopupState::SettingsList { selected } => {
                 let popup_area = centered_rect(25, 30, area);
                 f.render_widget(Clear, popup_area);
-                let options = ["Retention Days", "Scan Path", "Automatic Removal", "Excluded Paths"];
+                let options = ["Retention Days", "Scan Path", "Automatic Removal", "Excluded Paths", "Policies"];
                 let mut items = Vec::new();
                 for (i, &opt) in options.iter().enumerate() {
                     let style = if i == *selected {
@@ -137,12 +162,16 @@ impl PopupState {
                 );
                 f.render_widget(para, popup_area);
             }
-            PopupState::Scanning { logs } => {
+            PopupState::Scanning { logs, progress } => {
                 let popup_area = centered_rect(60, 40, area);
                 f.render_widget(Clear, popup_area);
                 let logs_guard = logs.lock().unwrap();
                 let logs_text = logs_guard.iter().rev().take(20).cloned().collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
-                let full_text = format!("Scanning for new artifacts\n\nPress any key to close\n\n{}", logs_text);
+                let full_text = format!(
+                    "{}\n\nPress c to cancel, any other key to close\n\n{}",
+                    progress_line(&progress.snapshot()),
+                    logs_text
+                );
                 let para = Paragraph::new(full_text).block(
                     Block::default()
                         .title("🔍 Scanning for new artifacts")
@@ -170,14 +199,41 @@ impl PopupState {
                     .style(Style::default().bg(Color::Red));
                 f.render_widget(list, popup_area);
             }
-            PopupState::ClearAllConfirmation => {
-                let popup_area = centered_rect(70, 35, area);
+            PopupState::Plan { plan, title, selected, .. } => {
+                let popup_area = centered_rect(80, 70, area);
                 f.render_widget(Clear, popup_area);
-                let text = "⚠️  CLEAR ALL BUILDS - PERMANENT DELETION\n\nThis will delete ALL artifacts from the filesystem.\nThis action cannot be undone.\n\nAre you absolutely sure? (y: Confirm, n: Cancel)";
-                let para = Paragraph::new(text)
-                    .block(Block::default().title("🔴 CLEAR ALL BUILDS").borders(Borders::ALL).style(Style::default().fg(Color::Black).bg(Color::Red)).padding(Padding::new(2, 2, 1, 1)))
+                let block = Block::default()
+                    .title(format!("{} - PLAN (↑↓ Space: Toggle a: All Enter: Confirm Esc: Cancel)", title))
+                    .borders(Borders::ALL)
                     .style(Style::default().fg(Color::Black).bg(Color::Red));
-                f.render_widget(para, popup_area);
+                let inner = block.inner(popup_area);
+                f.render_widget(block, popup_area);
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(2), Constraint::Min(0)])
+                    .split(inner);
+                f.render_widget(Paragraph::new(plan_summary(plan)), chunks[0]);
+                let items: Vec<ListItem> = plan
+                    .artifacts
+                    .iter()
+                    .map(|artifact| {
+                        ListItem::new(format!(
+                            "[{}] {} {:>9}  {:>4}d idle ({:<16})  {:<20}  {:<12}  {}",
+                            if artifact.selected { "x" } else { " " },
+                            if artifact.verified { "  " } else { "❔" },
+                            format_size(artifact.disk_bytes),
+                            artifact.idle_days,
+                            artifact.last_used_signal.as_str(),
+                            artifact.rule,
+                            artifact.policy.as_deref().unwrap_or("default"),
+                            artifact.path
+                        ))
+                    })
+                    .collect();
+                let list = List::new(items).highlight_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+                let mut state = ListState::default();
+                state.select(Some(*selected));
+                f.render_stateful_widget(list, chunks[1], &mut state);
             }
             PopupState::ConfirmAction { message, .. } => {
                 let popup_area = centered_rect(70, 35, area);
@@ -223,6 +279,74 @@ impl PopupState {
                     .block(Block::default().title("Excluded Paths (↑↓ Enter to remove Esc)").borders(Borders::ALL));
                 f.render_widget(list, popup_area);
             }
+            PopupState::Quarantine { entries, grace_days, selected } => {
+                let popup_area = centered_rect(70, 50, area);
+                f.render_widget(Clear, popup_area);
+                let mut items = Vec::new();
+                if entries.is_empty() {
+                    items.push(ListItem::new(Span::raw("(Nothing in quarantine)")));
+                } else {
+                    for (i, entry) in entries.iter().enumerate() {
+                        let style = if i == *selected {
+                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default()
+                        };
+                        let purge_date = entry.quarantined_at + chrono::Duration::days(*grace_days as i64);
+                        let text = format!(
+                            "{} · {} · purged after {}",
+                            entry.original_path,
+                            format_size(entry.size_bytes),
+                            purge_date.format("%Y-%m-%d %H:%M")
+                        );
+                        items.push(ListItem::new(Span::styled(text, style)));
+                    }
+                }
+                let list = List::new(items)
+                    .block(Block::default().title("🗄️ Quarantine (↑↓ Enter: Restore Esc)").borders(Borders::ALL));
+                f.render_widget(list, popup_area);
+            }
+            PopupState::Deletions { records, selected } => {
+                let popup_area = centered_rect(80, 60, area);
+                f.render_widget(Clear, popup_area);
+                let items: Vec<ListItem> = if records.is_empty() {
+                    vec![ListItem::new(Span::raw("(Nothing deleted yet)"))]
+                } else {
+                    records.iter().map(|record| ListItem::new(deletion_line(record))).collect()
+                };
+                let list = List::new(items)
+                    .block(Block::default().title("🗑️ Deletion Log (↑↓ Esc)").borders(Borders::ALL))
+                    .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+                let mut state = ListState::default();
+                state.select(Some(*selected));
+                f.render_stateful_widget(list, popup_area, &mut state);
+            }
+            PopupState::Policies { blocks, artifacts, selected } => {
+                let popup_area = centered_rect(80, 60, area);
+                f.render_widget(Clear, popup_area);
+                let block = Block::default().title("📜 Policies (↑↓ Esc)").borders(Borders::ALL);
+                let inner = block.inner(popup_area);
+                f.render_widget(block, popup_area);
+                let header = if blocks.is_empty() {
+                    "No [[policies]] blocks; every artifact follows the global settings.".to_string()
+                } else {
+                    format!("First match applies: {}", blocks.join(" → "))
+                };
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(2), Constraint::Min(0)])
+                    .split(inner);
+                f.render_widget(Paragraph::new(header), chunks[0]);
+                let items: Vec<ListItem> = if artifacts.is_empty() {
+                    vec![ListItem::new(Span::raw("(No artifacts yet)"))]
+                } else {
+                    artifacts.iter().map(|(policy, path)| ListItem::new(format!("{:<28}  {}", policy, path))).collect()
+                };
+                let list = List::new(items).highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+                let mut state = ListState::default();
+                state.select(Some(*selected));
+                f.render_stateful_widget(list, chunks[1], &mut state);
+            }
             PopupState::None => {}
         }
     }
@@ -234,11 +358,11 @@ impl PopupState {
                     if *selected > 0 {
                         *selected -= 1;
                     } else {
-                        *selected = 3; // Wrap to last
+                        *selected = 4; // Wrap to last
                     }
                 }
                 KeyCode::Down => {
-                    if *selected < 3 {
+                    if *selected < 4 {
                         *selected += 1;
                     } else {
                         *selected = 0; // Wrap to first
@@ -250,6 +374,7 @@ impl PopupState {
                         1 => Some(PopupCommand::OpenDirBrowse),
                         2 => Some(PopupCommand::ToggleRemoval),
                         3 => Some(PopupCommand::OpenExcludedPaths),
+                        4 => Some(PopupCommand::OpenPolicies),
                         _ => None,
                     };
                     if cmd.is_some() {
@@ -280,14 +405,16 @@ impl PopupState {
                 }
                 _ => {}
             },
-            PopupState::Logs { .. } => match key.code {
-                KeyCode::Esc => {
+            PopupState::Logs { .. } => {
+                if key.code == KeyCode::Esc {
                     *self = PopupState::None;
                 }
-                _ => {}
-            },
+            }
             PopupState::Scanning { .. } => {
                 *self = PopupState::None;
+                if key.code == KeyCode::Char('c') {
+                    return Some(PopupCommand::CancelScan);
+                }
                 return None;
             }
             PopupState::ArtifactActions { selected } => match key.code {
@@ -321,12 +448,24 @@ impl PopupState {
                 }
                 _ => {}
             },
-            PopupState::ClearAllConfirmation => match key.code {
-                KeyCode::Char('y') | KeyCode::Char('Y') => {
+            PopupState::Plan { plan, trigger, selected, .. } => match key.code {
+                KeyCode::Up if *selected > 0 => {
+                    *selected -= 1;
+                }
+                KeyCode::Down if *selected < plan.artifacts.len().saturating_sub(1) => {
+                    *selected += 1;
+                }
+                KeyCode::Char(' ') => plan.toggle(*selected),
+                KeyCode::Char('a') => plan.toggle_all(),
+                KeyCode::Enter => {
+                    let artifacts: Vec<PlannedArtifact> = plan.selected().cloned().collect();
+                    let trigger = *trigger;
                     *self = PopupState::None;
-                    return Some(PopupCommand::ClearAllBuilds);
+                    if !artifacts.is_empty() {
+                        return Some(PopupCommand::ExecutePlan { artifacts, trigger });
+                    }
                 }
-                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                KeyCode::Esc => {
                     *self = PopupState::None;
                 }
                 _ => {}
@@ -344,62 +483,53 @@ impl PopupState {
                     _ => {}
                 }
             }
-            PopupState::Progress { .. } => match key.code {
-                KeyCode::Esc => {
+            PopupState::Progress { .. } => {
+                if key.code == KeyCode::Esc {
                     *self = PopupState::None;
                 }
-                _ => {}
-            },
+            }
             PopupState::Info { .. } => {
                 *self = PopupState::None;
             },
             PopupState::DirBrowse { path, items, selected } => match key.code {
-                KeyCode::Up => {
-                    if *selected > 0 {
-                        *selected -= 1;
-                    }
+                KeyCode::Up if *selected > 0 => {
+                    *selected -= 1;
                 }
-                KeyCode::Down => {
-                    if *selected < items.len().saturating_sub(1) {
-                        *selected += 1;
-                    }
+                KeyCode::Down if *selected < items.len().saturating_sub(1) => {
+                    *selected += 1;
                 }
-                KeyCode::Enter => {
-                    if *selected < items.len() {
-                        let item = &items[*selected];
-                        if item == ".." {
-                            // Go up
-                            if let Some(parent) = std::path::Path::new(path).parent() {
-                                *path = parent.display().to_string();
-                                *items = get_dir_items(path);
-                                *selected = 0;
-                            }
-                        } else {
-                            // Enter dir
-                            let new_path = std::path::Path::new(path).join(item);
-                            if new_path.is_dir() {
-                                *path = new_path.display().to_string();
-                                *items = get_dir_items(path);
-                                *selected = 0;
-                            }
+                KeyCode::Enter if *selected < items.len() => {
+                    let item = &items[*selected];
+                    if item == ".." {
+                        // Go up
+                        if let Some(parent) = std::path::Path::new(path).parent() {
+                            *path = parent.display().to_string();
+                            *items = get_dir_items(path);
+                            *selected = 0;
+                        }
+                    } else {
+                        // Enter dir
+                        let new_path = std::path::Path::new(path).join(item);
+                        if new_path.is_dir() {
+                            *path = new_path.display().to_string();
+                            *items = get_dir_items(path);
+                            *selected = 0;
                         }
                     }
                 }
-                KeyCode::Char('s') => {
-                    if *selected < items.len() {
-                        let item = &items[*selected];
-                        let selected_path = if item == ".." {
-                            if let Some(parent) = std::path::Path::new(path).parent() {
-                                parent.display().to_string()
-                            } else {
-                                path.clone()
-                            }
+                KeyCode::Char('s') if *selected < items.len() => {
+                    let item = &items[*selected];
+                    let selected_path = if item == ".." {
+                        if let Some(parent) = std::path::Path::new(path).parent() {
+                            parent.display().to_string()
                         } else {
-                            std::path::Path::new(path).join(item).display().to_string()
-                        };
-                        *self = PopupState::None;
-                        return Some(PopupCommand::SetValue { key: "Scan Path".to_string(), value: selected_path });
-                    }
+                            path.clone()
+                        }
+                    } else {
+                        std::path::Path::new(path).join(item).display().to_string()
+                    };
+                    *self = PopupState::None;
+                    return Some(PopupCommand::SetValue { key: "Scan Path".to_string(), value: selected_path });
                 }
                 KeyCode::Char(' ') => {
                     // Select current directory
@@ -429,19 +559,58 @@ impl PopupState {
                         *selected = 0; // Wrap to first
                     }
                 }
-                KeyCode::Enter => {
-                    if !paths.is_empty() {
-                        let path = paths[*selected].clone();
-                        let message = format!("Remove '{}' from exclusion list?", path);
-                        *self = PopupState::new_confirm_action(message, format!("remove_excluded:{}", path));
-                        return None;
-                    }
+                KeyCode::Enter if !paths.is_empty() => {
+                    let path = paths[*selected].clone();
+                    let message = format!("Remove '{}' from exclusion list?", path);
+                    *self = PopupState::new_confirm_action(message, format!("remove_excluded:{}", path));
+                    return None;
+                }
+                KeyCode::Esc => {
+                    *self = PopupState::None;
+                }
+                _ => {}
+            },
+            PopupState::Quarantine { entries, selected, .. } => match key.code {
+                KeyCode::Up if *selected > 0 => {
+                    *selected -= 1;
+                }
+                KeyCode::Down if *selected < entries.len().saturating_sub(1) => {
+                    *selected += 1;
+                }
+                KeyCode::Enter if *selected < entries.len() => {
+                    let id = entries[*selected].id;
+                    *self = PopupState::None;
+                    return Some(PopupCommand::RestoreQuarantined { id });
                 }
                 KeyCode::Esc => {
                     *self = PopupState::None;
                 }
                 _ => {}
             },
+            PopupState::Deletions { records, selected } => match key.code {
+                KeyCode::Up if *selected > 0 => {
+                    *selected -= 1;
+                }
+                KeyCode::Down if *selected < records.len().saturating_sub(1) => {
+                    *selected += 1;
+                }
+                KeyCode::Esc | KeyCode::Enter => {
+                    *self = PopupState::None;
+                }
+                _ => {}
+            },
+            PopupState::Policies { artifacts, selected, .. } => match key.code {
+                KeyCode::Up if *selected > 0 => {
+                    *selected -= 1;
+                }
+                KeyCode::Down if *selected < artifacts.len().saturating_sub(1) => {
+                    *selected += 1;
+                }
+                KeyCode::Esc | KeyCode::Enter => {
+                    *self = PopupState::None;
+                }
+                _ => {}
+            },
             PopupState::None => {}
         }
         None
@@ -472,12 +641,65 @@ fn get_dir_items(path: &str) -> Vec<String> {
     let mut items = vec!["..".to_string()];
     if let Ok(entries) = std::fs::read_dir(path) {
         for entry in entries.flatten() {
-            if let Ok(file_type) = entry.file_type() {
-                if file_type.is_dir() {
-                    items.push(entry.file_name().to_string_lossy().to_string());
-                }
+            if let Ok(file_type) = entry.file_type()
+                && file_type.is_dir()
+            {
+                items.push(entry.file_name().to_string_lossy().to_string());
             }
         }
     }
     items
-}
\ No newline at end of file
+}
+
+/// One-line summary of a running scan, e.g. "1200 dirs · 3.4 GB · 12 artifacts · ~8s left".
+pub fn progress_line(snapshot: &ProgressSnapshot) -> String {
+    let eta = match snapshot.eta {
+        _ if snapshot.cancelled => "cancelling...".to_string(),
+        Some(eta) => format!("~{}s left", eta.as_secs()),
+        None => "estimating...".to_string(),
+    };
+    format!(
+        "{} dirs · {} · {} artifacts · {}",
+        snapshot.dirs_visited,
+        format_size(snapshot.bytes_counted),
+        snapshot.artifacts_found,
+        eta
+    )
+}
+
+/// Header of a deletion plan, e.g. "3 of 4 artifacts selected · 1.2 GB to free (900.0 MB on disk)",
+/// followed by " · 2 pinned kept" when the plan left pinned artifacts out.
+pub fn plan_summary(plan: &DeletionPlan) -> String {
+    let (size, disk) = plan.selected_bytes();
+    let mut summary = format!(
+        "{} of {} artifacts selected · {} to free ({} on disk)",
+        plan.selected().count(),
+        plan.artifacts.len(),
+        format_size(size),
+        format_size(disk)
+    );
+    if plan.pinned > 0 {
+        summary.push_str(&format!(" · {} pinned kept", plan.pinned));
+    }
+    if plan.unverified_kept() > 0 {
+        summary.push_str(&format!(" · {} unverified (❔) kept unless picked with Space", plan.unverified_kept()));
+    }
+    summary
+}
+
+/// One audit log entry, e.g. "2025-01-02 03:04 · alice · retention · removed · 1.2 GB · /code/app/target".
+pub fn deletion_line(record: &DeletionRecord) -> String {
+    let mut line = format!(
+        "{} · {} · {} · {} · {} · {}",
+        record.deleted_at.format("%Y-%m-%d %H:%M"),
+        record.user,
+        record.trigger,
+        record.outcome,
+        format_size(record.disk_bytes.unwrap_or(record.size_bytes)),
+        record.path
+    );
+    if let Some(detail) = &record.detail {
+        line.push_str(&format!(" ({})", detail));
+    }
+    line
+}
@@ -1,48 +1,82 @@
 // Utility helper functions
 
-use std::path::Path;
-use walkdir::WalkDir;
+/// Parses an age such as "14d", "2w" or "30" (days) into whole days, at
+/// least one.
+pub fn parse_age(input: &str) -> Result<u32, String> {
+    let input = input.trim();
+    let (number, multiplier) = match input.char_indices().last() {
+        Some((i, 'd')) | Some((i, 'D')) => (&input[..i], 1),
+        Some((i, 'w')) | Some((i, 'W')) => (&input[..i], 7),
+        _ => (input, 1),
+    };
+    match number.parse::<u32>().ok().and_then(|n| n.checked_mul(multiplier)) {
+        Some(0) => Err(format!("Invalid age '{}', must be at least 1 day", input)),
+        Some(days) => Ok(days),
+        None => Err(format!("Invalid age '{}', expected e.g. 14d or 2w", input)),
+    }
+}
+
+/// Parses an interval such as "30m", "6h", "1d" or "2w". A unit is required.
+pub fn parse_interval(input: &str) -> Result<std::time::Duration, String> {
+    let input = input.trim();
+    let invalid = || format!("Invalid interval '{}', expected e.g. 30m, 6h or 1d", input);
+    let (number, seconds) = match input.char_indices().last() {
+        Some((i, 's')) => (&input[..i], 1),
+        Some((i, 'm')) => (&input[..i], 60),
+        Some((i, 'h')) => (&input[..i], 60 * 60),
+        Some((i, 'd')) => (&input[..i], 24 * 60 * 60),
+        Some((i, 'w')) => (&input[..i], 7 * 24 * 60 * 60),
+        _ => return Err(invalid()),
+    };
+    let number: u64 = number.trim().parse().map_err(|_| invalid())?;
+    number.checked_mul(seconds).map(std::time::Duration::from_secs).ok_or_else(invalid)
+}
 
-pub fn detect_language_for_path(path: &str) -> String {
-    let p = Path::new(path);
-    if p.join("Cargo.toml").exists() {
-        "Rust".to_string()
-    } else if p.join("package.json").exists() {
-        "JavaScript".to_string()
-    } else if p.join("pyproject.toml").exists() {
-        "Python".to_string()
-    } else if p.join("go.mod").exists() {
-        "Go".to_string()
-    } else if p.join("Makefile").exists() || p.join("CMakeLists.txt").exists() || p.join("configure.ac").exists() {
-        "C/C++".to_string()
-    } else if p.join("pom.xml").exists() || p.join("build.gradle").exists() {
-        "Java".to_string()
-    } else if p.join("composer.json").exists() {
-        "PHP".to_string()
-    } else if p.join("Gemfile").exists() {
-        "Ruby".to_string()
-    } else if p.join("Package.swift").exists() {
-        "Swift".to_string()
-    } else if p.join("build.gradle.kts").exists() {
-        "Kotlin".to_string()
-    } else if p.join("build.sbt").exists() {
-        "Scala".to_string()
-    } else if p.join("stack.yaml").exists() {
-        "Haskell".to_string()
-    } else if p.join("mix.exs").exists() {
-        "Elixir".to_string()
+/// Parses a size such as "50GB", "512 MiB" or "1073741824" (bytes). Units
+/// without an "i" are powers of 1000, like `format_size`.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let invalid = || format!("Invalid size '{}', expected e.g. 50GB or 512MiB", input);
+    let split = input.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(input.len());
+    let (number, unit) = (&input[..split], input[split..].trim());
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "K" => 1000,
+        "MB" | "M" => 1000u64.pow(2),
+        "GB" | "G" => 1000u64.pow(3),
+        "TB" | "T" => 1000u64.pow(4),
+        "KIB" => 1 << 10,
+        "MIB" => 1 << 20,
+        "GIB" => 1 << 30,
+        "TIB" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let bytes = number * multiplier as f64;
+    if !bytes.is_finite() || bytes > u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(bytes.round() as u64)
+}
+
+/// Formats a byte count for humans, e.g. "1.5 GB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
     } else {
-        "Unknown".to_string()
+        format!("{:.1} {}", size, UNITS[unit])
     }
 }
 
-pub fn calculate_dir_size(path: &str) -> u64 {
-    use std::fs;
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter_map(|e| fs::metadata(e.path()).ok())
-        .map(|m| m.len())
-        .sum()
+/// Formats a size difference with its sign, e.g. "+1.5 GB" or "-512 B".
+pub fn format_size_change(bytes: i64) -> String {
+    let sign = if bytes < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_size(bytes.unsigned_abs()))
 }
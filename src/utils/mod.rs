@@ -4,4 +4,4 @@ pub mod logger;
 pub mod helpers;
 
 // Re-export commonly used functions
-pub use helpers::{detect_language_for_path, calculate_dir_size};
+pub use helpers::{format_size, format_size_change, parse_age, parse_interval, parse_size};